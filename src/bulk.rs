@@ -0,0 +1,384 @@
+use std::{
+    io::Error,
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not},
+    sync::OnceLock,
+};
+
+use wide::u64x8;
+
+use crate::{
+    bitsliced_add_inline, bitsliced_add_single_inline, check_modulo, check_modulo_power_of_two,
+    des_reduction_inline, fastrange_reduction_inline, lane::Lane, modulo_inline_unchecked,
+    modulo_power_of_two_inline_unchecked,
+};
+
+//bulk versions of the arithmetic family, they work on many [u64x8; N] blocks at once
+//and pick the widest registers the cpu supports at runtime, so one binary runs at best speed everywhere
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimdWidth {
+    Scalar,
+    Sse2,
+    Avx2,
+    Avx512,
+}
+
+static SIMD_WIDTH: OnceLock<SimdWidth> = OnceLock::new();
+
+//widest registers the bulk functions use on this cpu
+pub fn simd_width() -> SimdWidth {
+    *SIMD_WIDTH.get_or_init(|| {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if std::is_x86_feature_detected!("avx512f") {
+                SimdWidth::Avx512
+            } else if std::is_x86_feature_detected!("avx2") {
+                SimdWidth::Avx2
+            } else {
+                SimdWidth::Sse2
+            }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        {
+            SimdWidth::Scalar
+        }
+    })
+}
+
+pub fn bitsliced_add_bulk<const N: usize>(a: &mut [[u64x8; N]], b: &[[u64x8; N]]) {
+    assert_eq!(
+        a.len(),
+        b.len(),
+        "a and b must hold the same number of blocks"
+    );
+    let b = as_rows(b);
+    for_each_block(
+        a,
+        #[inline(always)]
+        |i, block| bitsliced_add_inline(block, &b[i]),
+    );
+}
+
+pub fn bitsliced_add_single_bulk<const N: usize>(a: &mut [[u64x8; N]], b: u64) {
+    for_each_block(
+        a,
+        #[inline(always)]
+        |_, block| bitsliced_add_single_inline(block, b),
+    );
+}
+
+pub fn bitsliced_modulo_power_of_two_bulk<const N: usize>(
+    a: &mut [[u64x8; N]],
+    k: usize,
+) -> Result<(), Error> {
+    //validate once up front, so every block either gets reduced or none does
+    check_modulo_power_of_two::<N>(k)?;
+    for_each_block(
+        a,
+        #[inline(always)]
+        |_, block| modulo_power_of_two_inline_unchecked(block, k),
+    );
+    Ok(())
+}
+
+pub fn bitsliced_modulo_bulk<const N: usize>(a: &mut [[u64x8; N]], m: u64) -> Result<(), Error> {
+    check_modulo(m)?;
+    for_each_block(
+        a,
+        #[inline(always)]
+        |_, block| modulo_inline_unchecked(block, m),
+    );
+    Ok(())
+}
+
+pub fn des_reduction_bulk(h: &mut [[u64x8; 64]], i: u64) {
+    for_each_block(
+        h,
+        #[inline(always)]
+        |_, block| des_reduction_inline(block, i),
+    );
+}
+
+pub fn reduction_mod_bulk<const N: usize>(
+    h: &mut [[u64x8; N]],
+    i: u64,
+    m: u64,
+) -> Result<(), Error> {
+    check_modulo(m)?;
+    for_each_block(
+        h,
+        #[inline(always)]
+        |_, block| {
+            bitsliced_add_single_inline(block, i);
+            modulo_inline_unchecked(block, m);
+        },
+    );
+    Ok(())
+}
+
+pub fn fastrange_reduction_bulk<const N: usize>(h: &mut [[u64x8; N]], i: u64, n: u64) {
+    for_each_block(
+        h,
+        #[inline(always)]
+        |_, block| fastrange_reduction_inline(block, i, n),
+    );
+}
+
+//same bits as u64x8, but as a plain array the compiler vectorizes it for whatever
+//target features are enabled, wide::u64x8 is fixed to the features known at compile time
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C, align(64))]
+struct Row([u64; 8]);
+
+impl Lane for Row {
+    const ZERO: Self = Row([0; 8]);
+    const ALL_ONES: Self = Row([u64::MAX; 8]);
+}
+
+impl BitAnd for Row {
+    type Output = Self;
+    #[inline(always)]
+    fn bitand(mut self, rhs: Self) -> Self {
+        self &= rhs;
+        self
+    }
+}
+
+impl BitOr for Row {
+    type Output = Self;
+    #[inline(always)]
+    fn bitor(mut self, rhs: Self) -> Self {
+        self |= rhs;
+        self
+    }
+}
+
+impl BitXor for Row {
+    type Output = Self;
+    #[inline(always)]
+    fn bitxor(mut self, rhs: Self) -> Self {
+        self ^= rhs;
+        self
+    }
+}
+
+impl Not for Row {
+    type Output = Self;
+    #[inline(always)]
+    fn not(self) -> Self {
+        Row(self.0.map(|x| !x))
+    }
+}
+
+impl BitAndAssign for Row {
+    #[inline(always)]
+    fn bitand_assign(&mut self, rhs: Self) {
+        for (x, y) in self.0.iter_mut().zip(rhs.0) {
+            *x &= y;
+        }
+    }
+}
+
+impl BitOrAssign for Row {
+    #[inline(always)]
+    fn bitor_assign(&mut self, rhs: Self) {
+        for (x, y) in self.0.iter_mut().zip(rhs.0) {
+            *x |= y;
+        }
+    }
+}
+
+impl BitXorAssign for Row {
+    #[inline(always)]
+    fn bitxor_assign(&mut self, rhs: Self) {
+        for (x, y) in self.0.iter_mut().zip(rhs.0) {
+            *x ^= y;
+        }
+    }
+}
+
+const _: () =
+    assert!(size_of::<Row>() == size_of::<u64x8>() && align_of::<Row>() == align_of::<u64x8>());
+
+fn as_rows<const N: usize>(blocks: &[[u64x8; N]]) -> &[[Row; N]] {
+    //SAFETY: u64x8 and Row are both 64 bytes of plain u64s with 64-byte alignment (asserted above)
+    unsafe { std::slice::from_raw_parts(blocks.as_ptr() as *const [Row; N], blocks.len()) }
+}
+
+fn as_rows_mut<const N: usize>(blocks: &mut [[u64x8; N]]) -> &mut [[Row; N]] {
+    //SAFETY: see as_rows
+    unsafe { std::slice::from_raw_parts_mut(blocks.as_mut_ptr() as *mut [Row; N], blocks.len()) }
+}
+
+//calls f with the index and the rows of every block, compiled for the widest registers available
+fn for_each_block<const N: usize, F>(blocks: &mut [[u64x8; N]], f: F)
+where
+    F: Fn(usize, &mut [Row; N]),
+{
+    let rows = as_rows_mut(blocks);
+    match simd_width() {
+        #[cfg(target_arch = "x86_64")]
+        SimdWidth::Avx512 => unsafe { for_each_block_avx512(rows, f) },
+        #[cfg(target_arch = "x86_64")]
+        SimdWidth::Avx2 => unsafe { for_each_block_avx2(rows, f) },
+        _ => for_each_block_default(rows, f),
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn for_each_block_avx512<const N: usize, F>(rows: &mut [[Row; N]], f: F)
+where
+    F: Fn(usize, &mut [Row; N]),
+{
+    for (i, block) in rows.iter_mut().enumerate() {
+        f(i, block);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn for_each_block_avx2<const N: usize, F>(rows: &mut [[Row; N]], f: F)
+where
+    F: Fn(usize, &mut [Row; N]),
+{
+    for (i, block) in rows.iter_mut().enumerate() {
+        f(i, block);
+    }
+}
+
+fn for_each_block_default<const N: usize, F>(rows: &mut [[Row; N]], f: F)
+where
+    F: Fn(usize, &mut [Row; N]),
+{
+    for (i, block) in rows.iter_mut().enumerate() {
+        f(i, block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bitsliced_add, bitsliced_add_single, bitsliced_modulo, bitsliced_modulo_power_of_two,
+        des_reduction, fastrange_reduction, reduction_mod, reduction_mod_inline,
+        test_util::random_blocks,
+    };
+
+    #[test]
+    fn test_simd_width_works() {
+        #[cfg(target_arch = "x86_64")]
+        {
+            let expected = if std::is_x86_feature_detected!("avx512f") {
+                SimdWidth::Avx512
+            } else if std::is_x86_feature_detected!("avx2") {
+                SimdWidth::Avx2
+            } else {
+                SimdWidth::Sse2
+            };
+            assert_eq!(simd_width(), expected);
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        assert_eq!(simd_width(), SimdWidth::Scalar);
+    }
+
+    #[test]
+    fn test_add_bulk_works() {
+        let mut a = random_blocks(5);
+        let b = random_blocks(6)[1..].to_vec();
+        let expected: Vec<_> = a.iter().zip(&b).map(|(x, y)| bitsliced_add(x, y)).collect();
+        bitsliced_add_bulk(&mut a, &b);
+        assert_eq!(a, expected);
+
+        let expected: Vec<_> = a.iter().map(|x| bitsliced_add_single(x, 12345)).collect();
+        bitsliced_add_single_bulk(&mut a, 12345);
+        assert_eq!(a, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_add_bulk_length_mismatch_panics() {
+        let mut a = random_blocks(2);
+        bitsliced_add_bulk(&mut a, &random_blocks(3));
+    }
+
+    #[test]
+    fn test_modulo_bulk_works() {
+        let mut a = random_blocks(3);
+        let expected: Vec<_> = a
+            .iter()
+            .map(|x| bitsliced_modulo(x, 95u64.pow(8)).unwrap())
+            .collect();
+        bitsliced_modulo_bulk(&mut a, 95u64.pow(8)).unwrap();
+        assert_eq!(a, expected);
+
+        let expected: Vec<_> = a
+            .iter()
+            .map(|x| bitsliced_modulo_power_of_two(x, 20).unwrap())
+            .collect();
+        bitsliced_modulo_power_of_two_bulk(&mut a, 20).unwrap();
+        assert_eq!(a, expected);
+    }
+
+    #[test]
+    fn test_modulo_bulk_invalid_input_fails() {
+        let mut a = random_blocks(2);
+        let copy = a.clone();
+        assert!(bitsliced_modulo_bulk(&mut a, 0).is_err());
+        assert!(bitsliced_modulo_power_of_two_bulk(&mut a, 65).is_err());
+        assert!(reduction_mod_bulk(&mut a, 1, 0).is_err());
+        assert_eq!(a, copy);
+    }
+
+    #[test]
+    fn test_reduction_bulk_works() {
+        let mut a = random_blocks(3);
+        let expected: Vec<_> = a.iter().map(|x| des_reduction(x, 7)).collect();
+        des_reduction_bulk(&mut a, 7);
+        assert_eq!(a, expected);
+
+        let mut a = random_blocks(3);
+        let expected: Vec<_> = a
+            .iter()
+            .map(|x| reduction_mod(x, 7, 26u64.pow(7)).unwrap())
+            .collect();
+        reduction_mod_bulk(&mut a, 7, 26u64.pow(7)).unwrap();
+        assert_eq!(a, expected);
+
+        let mut a = random_blocks(3);
+        let expected: Vec<_> = a
+            .iter()
+            .map(|x| fastrange_reduction(x, 7, 26u64.pow(7)))
+            .collect();
+        fastrange_reduction_bulk(&mut a, 7, 26u64.pow(7));
+        assert_eq!(a, expected);
+    }
+
+    #[test]
+    fn test_every_backend_works() {
+        let blocks = random_blocks(4);
+        let expected: Vec<_> = blocks
+            .iter()
+            .map(|x| reduction_mod(x, 3, 26u64.pow(7)).unwrap())
+            .collect();
+        let kernel =
+            |_: usize, block: &mut [Row; 64]| reduction_mod_inline(block, 3, 26u64.pow(7)).unwrap();
+
+        let mut a = blocks.clone();
+        for_each_block_default(as_rows_mut(&mut a), kernel);
+        assert_eq!(a, expected);
+        #[cfg(target_arch = "x86_64")]
+        {
+            if std::is_x86_feature_detected!("avx2") {
+                let mut a = blocks.clone();
+                unsafe { for_each_block_avx2(as_rows_mut(&mut a), kernel) };
+                assert_eq!(a, expected);
+            }
+            if std::is_x86_feature_detected!("avx512f") {
+                let mut a = blocks.clone();
+                unsafe { for_each_block_avx512(as_rows_mut(&mut a), kernel) };
+                assert_eq!(a, expected);
+            }
+        }
+    }
+}
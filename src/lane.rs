@@ -0,0 +1,45 @@
+use std::{
+    fmt::Debug,
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not},
+};
+
+use wide::{u64x2, u64x4, u64x8};
+
+//register that holds one row of a bitsliced block, every bit is a different column
+//all bitsliced operations are generic over it, so the register width can be picked per machine
+//u64 = 64 columns, u64x2 = 128 (SSE2/NEON), u64x4 = 256 (AVX2), u64x8 = 512 (AVX-512)
+pub trait Lane:
+    Copy
+    + Debug
+    + PartialEq
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+    + BitAndAssign
+    + BitOrAssign
+    + BitXorAssign
+{
+    const ZERO: Self;
+    const ALL_ONES: Self;
+}
+
+impl Lane for u64 {
+    const ZERO: Self = 0;
+    const ALL_ONES: Self = u64::MAX;
+}
+
+impl Lane for u64x2 {
+    const ZERO: Self = u64x2::ZERO;
+    const ALL_ONES: Self = u64x2::splat(u64::MAX);
+}
+
+impl Lane for u64x4 {
+    const ZERO: Self = u64x4::ZERO;
+    const ALL_ONES: Self = u64x4::splat(u64::MAX);
+}
+
+impl Lane for u64x8 {
+    const ZERO: Self = u64x8::ZERO;
+    const ALL_ONES: Self = u64x8::splat(u64::MAX);
+}
@@ -1,15 +1,22 @@
 use std::{
     io::{Error, ErrorKind},
+    str::FromStr,
     sync::OnceLock,
 };
 
 use wide::u64x8;
 
-use crate::transpose::transpose_scalar;
+use crate::lane::Lane;
 
 pub mod benchmark;
+pub mod bulk;
+pub mod lane;
+pub mod reference;
 pub mod transpose;
 
+#[cfg(test)]
+mod test_util;
+
 pub const ALL_ONES: u64x8 = u64x8::splat(0xFFFFFFFFFFFFFFFF);
 pub const ZERO: u64x8 = u64x8::ZERO;
 
@@ -17,12 +24,17 @@ pub fn splat(n: u64) -> u64x8 {
     u64x8::splat(n)
 }
 
+//all operations are generic over the number of rows N, which is the bit width of the columns
+//e.g N = 32 for MD5/SHA words, N = 56 for DES keys or N = 128 for full digests
+//and over the lane type L that holds a row (u64, u64x2, u64x4 or u64x8, see lane.rs)
+//with [u64x8; 64] both are inferred, so the 64-row u64x8 API works as before
+
 //expects the input to be in bitsliced form e.g integers are columns, not rows
 //last row is LSB
-pub fn bitsliced_add(a: &[u64x8; 64], b: &[u64x8; 64]) -> [u64x8; 64] {
-    let mut carry = u64x8::ZERO;
-    let mut sum = [u64x8::ZERO; 64];
-    for i in (0..64).rev() {
+pub fn bitsliced_add<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> [L; N] {
+    let mut carry = L::ZERO;
+    let mut sum = [L::ZERO; N];
+    for i in (0..N).rev() {
         let res = calc_sum_carry(a[i], b[i], carry);
         sum[i] = res.0;
         //only set carry if we haven't reached the end yet, we currently ignore overflows
@@ -31,122 +43,1197 @@ pub fn bitsliced_add(a: &[u64x8; 64], b: &[u64x8; 64]) -> [u64x8; 64] {
     sum
 }
 
-pub fn bitsliced_add_single(a: &[u64x8; 64], b: u64) -> [u64x8; 64] {
-    let mut carry = u64x8::ZERO;
-    let mut sum = [u64x8::ZERO; 64];
-    for i in (0..64).rev() {
-        let shift_right = 63 - i;
-        let current_bit = (b >> shift_right) & 1;
-        let b_i = if current_bit == 1 { ALL_ONES } else { ZERO };
-        let res = calc_sum_carry(a[i], b_i, carry);
-        sum[i] = res.0;
-        //only set carry if we haven't reached the end yet, we currently ignore overflows
-        carry = res.1;
-    }
-    sum
+pub fn bitsliced_add_single<L: Lane, const N: usize>(a: &[L; N], b: u64) -> [L; N] {
+    let mut carry = L::ZERO;
+    let mut sum = [L::ZERO; N];
+    for i in (0..N).rev() {
+        let b_i = bit_mask(b, N - 1 - i);
+        let res = calc_sum_carry(a[i], b_i, carry);
+        sum[i] = res.0;
+        //only set carry if we haven't reached the end yet, we currently ignore overflows
+        carry = res.1;
+    }
+    sum
+}
+
+#[inline(always)]
+pub fn bitsliced_add_inline<L: Lane, const N: usize>(a: &mut [L; N], b: &[L; N]) {
+    let mut carry = L::ZERO;
+    for i in (0..N).rev() {
+        let res = calc_sum_carry(a[i], b[i], carry);
+        a[i] = res.0;
+        //only set carry if we haven't reached the end yet, we currently ignore overflows
+        carry = res.1;
+    }
+}
+
+#[inline(always)]
+pub fn bitsliced_add_single_inline<L: Lane, const N: usize>(a: &mut [L; N], b: u64) {
+    let mut carry = L::ZERO;
+    for i in (0..N).rev() {
+        let b_i = bit_mask(b, N - 1 - i);
+        let res = calc_sum_carry(a[i], b_i, carry);
+        a[i] = res.0;
+        //only set carry if we haven't reached the end yet, we currently ignore overflows
+        carry = res.1;
+    }
+}
+
+//same as bitsliced_add, but also returns the carry-out mask
+//a bit in the mask is set if that column overflowed (wrapped around)
+pub fn bitsliced_add_checked<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> ([L; N], L) {
+    let mut carry = L::ZERO;
+    let mut sum = [L::ZERO; N];
+    for i in (0..N).rev() {
+        let res = calc_sum_carry(a[i], b[i], carry);
+        sum[i] = res.0;
+        carry = res.1;
+    }
+    (sum, carry)
+}
+
+pub fn bitsliced_add_single_checked<L: Lane, const N: usize>(a: &[L; N], b: u64) -> ([L; N], L) {
+    let mut carry = L::ZERO;
+    let mut sum = [L::ZERO; N];
+    for i in (0..N).rev() {
+        let b_i = bit_mask(b, N - 1 - i);
+        let res = calc_sum_carry(a[i], b_i, carry);
+        sum[i] = res.0;
+        carry = res.1;
+    }
+    if exceeds_rows::<N>(b) {
+        carry = L::ALL_ONES;
+    }
+    (sum, carry)
+}
+
+pub fn bitsliced_add_checked_inline<L: Lane, const N: usize>(a: &mut [L; N], b: &[L; N]) -> L {
+    let mut carry = L::ZERO;
+    for i in (0..N).rev() {
+        let res = calc_sum_carry(a[i], b[i], carry);
+        a[i] = res.0;
+        carry = res.1;
+    }
+    carry
+}
+
+pub fn bitsliced_add_single_checked_inline<L: Lane, const N: usize>(a: &mut [L; N], b: u64) -> L {
+    let mut carry = L::ZERO;
+    for i in (0..N).rev() {
+        let b_i = bit_mask(b, N - 1 - i);
+        let res = calc_sum_carry(a[i], b_i, carry);
+        a[i] = res.0;
+        carry = res.1;
+    }
+    if exceeds_rows::<N>(b) {
+        carry = L::ALL_ONES;
+    }
+    carry
+}
+
+//columns that overflowed are clamped to 2^N - 1 (all rows set)
+pub fn bitsliced_add_saturating<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> [L; N] {
+    let (mut sum, carry) = bitsliced_add_checked(a, b);
+    saturate_inline(&mut sum, carry);
+    sum
+}
+
+pub fn bitsliced_add_single_saturating<L: Lane, const N: usize>(a: &[L; N], b: u64) -> [L; N] {
+    let (mut sum, carry) = bitsliced_add_single_checked(a, b);
+    saturate_inline(&mut sum, carry);
+    sum
+}
+
+pub fn bitsliced_add_saturating_inline<L: Lane, const N: usize>(a: &mut [L; N], b: &[L; N]) {
+    let carry = bitsliced_add_checked_inline(a, b);
+    saturate_inline(a, carry);
+}
+
+pub fn bitsliced_add_single_saturating_inline<L: Lane, const N: usize>(a: &mut [L; N], b: u64) {
+    let carry = bitsliced_add_single_checked_inline(a, b);
+    saturate_inline(a, carry);
+}
+
+//sets every bit of the columns in mask
+fn saturate_inline<L: Lane, const N: usize>(a: &mut [L; N], mask: L) {
+    for row in a.iter_mut() {
+        *row |= mask;
+    }
+}
+
+//wrapping variants are the same as the plain ones, they exist to make the intent explicit
+pub fn bitsliced_add_wrapping<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> [L; N] {
+    bitsliced_add(a, b)
+}
+
+pub fn bitsliced_add_single_wrapping<L: Lane, const N: usize>(a: &[L; N], b: u64) -> [L; N] {
+    bitsliced_add_single(a, b)
+}
+
+pub fn bitsliced_add_wrapping_inline<L: Lane, const N: usize>(a: &mut [L; N], b: &[L; N]) {
+    bitsliced_add_inline(a, b);
+}
+
+pub fn bitsliced_add_single_wrapping_inline<L: Lane, const N: usize>(a: &mut [L; N], b: u64) {
+    bitsliced_add_single_inline(a, b);
+}
+
+//true if b has bits set above the lowest N, then b is larger than every column
+//the single ops drop those bits from the sum/difference, but the carry/borrow mask still reports them
+#[inline(always)]
+fn exceeds_rows<const N: usize>(b: u64) -> bool {
+    N < 64 && b >> N != 0
+}
+
+//row with every column set to bit shift of b, bits past the 64th are 0
+#[inline(always)]
+fn bit_mask<L: Lane>(b: u64, shift: usize) -> L {
+    if shift < 64 && (b >> shift) & 1 == 1 {
+        L::ALL_ONES
+    } else {
+        L::ZERO
+    }
+}
+
+#[inline(always)]
+fn calc_sum_carry<L: Lane>(a: L, b: L, carry: L) -> (L, L) {
+    let sum = a ^ b ^ carry;
+    let next_carry = (a & b) | (carry & (a ^ b));
+    (sum, next_carry)
+}
+
+//subtracts b from a for every column (a - b), wraps around on underflow
+//expects the input to be in bitsliced form e.g integers are columns, not rows
+//last row is LSB
+pub fn bitsliced_sub<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> [L; N] {
+    bitsliced_sub_with_borrow(a, b).0
+}
+
+pub fn bitsliced_sub_single<L: Lane, const N: usize>(a: &[L; N], b: u64) -> [L; N] {
+    bitsliced_sub_single_with_borrow(a, b).0
+}
+
+pub fn bitsliced_sub_inline<L: Lane, const N: usize>(a: &mut [L; N], b: &[L; N]) {
+    bitsliced_sub_with_borrow_inline(a, b);
+}
+
+pub fn bitsliced_sub_single_inline<L: Lane, const N: usize>(a: &mut [L; N], b: u64) {
+    bitsliced_sub_single_with_borrow_inline(a, b);
+}
+
+//same as bitsliced_sub, but also returns the borrow-out mask
+//a bit in the mask is set if that column underflowed (a < b)
+pub fn bitsliced_sub_with_borrow<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> ([L; N], L) {
+    let mut borrow = L::ZERO;
+    let mut diff = [L::ZERO; N];
+    for i in (0..N).rev() {
+        let res = calc_diff_borrow(a[i], b[i], borrow);
+        diff[i] = res.0;
+        borrow = res.1;
+    }
+    (diff, borrow)
+}
+
+pub fn bitsliced_sub_single_with_borrow<L: Lane, const N: usize>(
+    a: &[L; N],
+    b: u64,
+) -> ([L; N], L) {
+    let mut borrow = L::ZERO;
+    let mut diff = [L::ZERO; N];
+    for i in (0..N).rev() {
+        let b_i = bit_mask(b, N - 1 - i);
+        let res = calc_diff_borrow(a[i], b_i, borrow);
+        diff[i] = res.0;
+        borrow = res.1;
+    }
+    if exceeds_rows::<N>(b) {
+        borrow = L::ALL_ONES;
+    }
+    (diff, borrow)
+}
+
+pub fn bitsliced_sub_with_borrow_inline<L: Lane, const N: usize>(a: &mut [L; N], b: &[L; N]) -> L {
+    let mut borrow = L::ZERO;
+    for i in (0..N).rev() {
+        let res = calc_diff_borrow(a[i], b[i], borrow);
+        a[i] = res.0;
+        borrow = res.1;
+    }
+    borrow
+}
+
+pub fn bitsliced_sub_single_with_borrow_inline<L: Lane, const N: usize>(
+    a: &mut [L; N],
+    b: u64,
+) -> L {
+    let mut borrow = L::ZERO;
+    for i in (0..N).rev() {
+        let b_i = bit_mask(b, N - 1 - i);
+        let res = calc_diff_borrow(a[i], b_i, borrow);
+        a[i] = res.0;
+        borrow = res.1;
+    }
+    if exceeds_rows::<N>(b) {
+        borrow = L::ALL_ONES;
+    }
+    borrow
+}
+
+//full subtractor: borrow is set when a < b + borrow_in
+#[inline(always)]
+fn calc_diff_borrow<L: Lane>(a: L, b: L, borrow: L) -> (L, L) {
+    let diff = a ^ b ^ borrow;
+    let next_borrow = (!a & b) | (borrow & !(a ^ b));
+    (diff, next_borrow)
+}
+
+//multiplies every column with b using shift-and-add, only the lower N bits of the product are kept
+pub fn bitsliced_mul_single<L: Lane, const N: usize>(a: &[L; N], b: u64) -> [L; N] {
+    let mut product = [L::ZERO; N];
+    for k in 0..N.min(64) {
+        if (b >> k) & 1 == 1 {
+            add_shifted_inline(&mut product, a, k);
+        }
+    }
+    product
+}
+
+pub fn bitsliced_mul_single_inline<L: Lane, const N: usize>(a: &mut [L; N], b: u64) {
+    *a = bitsliced_mul_single(a, b);
+}
+
+//adds (a << k) to acc, bits shifted past the MSB are dropped
+fn add_shifted_inline<L: Lane, const N: usize>(acc: &mut [L; N], a: &[L; N], k: usize) {
+    let mut carry = L::ZERO;
+    //the lowest k rows of the shifted value are zero, so they don't change acc
+    for i in (0..N - k).rev() {
+        let res = calc_sum_carry(acc[i], a[i + k], carry);
+        acc[i] = res.0;
+        carry = res.1;
+    }
+}
+
+//multiplies a and b column by column
+//returns (low, high), the lower and upper N bits of the 2N-bit product of every column
+pub fn bitsliced_mul<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> ([L; N], [L; N]) {
+    let mut low = [L::ZERO; N];
+    let mut high = [L::ZERO; N];
+    for k in 0..N {
+        //bit k of every column of b selects whether (a << k) gets added
+        add_partial_product_inline(&mut high, &mut low, a, b[N - 1 - k], k);
+    }
+    (low, high)
+}
+
+//adds ((a & mask) << k) to the 2N-row product high:low
+//the product must fit in N + k bits before calling this, which holds when k is increasing
+#[inline(always)]
+fn add_partial_product_inline<L: Lane, const N: usize>(
+    high: &mut [L; N],
+    low: &mut [L; N],
+    a: &[L; N],
+    mask: L,
+    k: usize,
+) {
+    let mut carry = L::ZERO;
+    for i in (0..N).rev() {
+        let row = N + i - k;
+        let p = if row < N {
+            &mut high[row]
+        } else {
+            &mut low[row - N]
+        };
+        let res = calc_sum_carry(*p, a[i] & mask, carry);
+        *p = res.0;
+        carry = res.1;
+    }
+    //the partial sum before this step fits in N + k bits, so this row is still empty
+    high[N - 1 - k] = carry;
+}
+
+//maps every column into [0, n) by calculating (a * n) >> N
+//cheaper than a modulo and close to uniform if a is uniformly distributed over all N bits
+//n is taken modulo 2^N when N < 64
+//see https://lemire.me/blog/2016/06/27/a-fast-alternative-to-the-modulo-reduction/
+#[inline(always)]
+pub fn bitsliced_fastrange<L: Lane, const N: usize>(a: &[L; N], n: u64) -> [L; N] {
+    let mut low = [L::ZERO; N];
+    let mut high = [L::ZERO; N];
+    for k in 0..N.min(64) {
+        if (n >> k) & 1 == 1 {
+            add_partial_product_inline(&mut high, &mut low, a, L::ALL_ONES, k);
+        }
+    }
+    high
+}
+
+#[inline(always)]
+pub fn bitsliced_fastrange_inline<L: Lane, const N: usize>(a: &mut [L; N], n: u64) {
+    *a = bitsliced_fastrange(a, n);
+}
+
+//comparisons return a mask with the bit of every column set where the comparison holds
+pub fn bitsliced_eq<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> L {
+    let mut diff = L::ZERO;
+    for (a_i, b_i) in a.iter().zip(b) {
+        diff |= *a_i ^ *b_i;
+    }
+    !diff
+}
+
+pub fn bitsliced_lt<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> L {
+    calc_lt_gt(a, b).0
+}
+
+pub fn bitsliced_le<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> L {
+    !calc_lt_gt(a, b).1
+}
+
+pub fn bitsliced_gt<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> L {
+    calc_lt_gt(a, b).1
+}
+
+pub fn bitsliced_ge<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> L {
+    !calc_lt_gt(a, b).0
+}
+
+//b is compared as a full 64-bit value, so columns with N < 64 rows never equal a larger b
+pub fn bitsliced_eq_single<L: Lane, const N: usize>(a: &[L; N], b: u64) -> L {
+    if exceeds_rows::<N>(b) {
+        return L::ZERO;
+    }
+    let mut diff = L::ZERO;
+    for (i, a_i) in a.iter().enumerate() {
+        let b_i = bit_mask(b, N - 1 - i);
+        diff |= *a_i ^ b_i;
+    }
+    !diff
+}
+
+pub fn bitsliced_lt_single<L: Lane, const N: usize>(a: &[L; N], b: u64) -> L {
+    calc_lt_gt_single(a, b).0
+}
+
+pub fn bitsliced_le_single<L: Lane, const N: usize>(a: &[L; N], b: u64) -> L {
+    !calc_lt_gt_single(a, b).1
+}
+
+pub fn bitsliced_gt_single<L: Lane, const N: usize>(a: &[L; N], b: u64) -> L {
+    calc_lt_gt_single(a, b).1
+}
+
+pub fn bitsliced_ge_single<L: Lane, const N: usize>(a: &[L; N], b: u64) -> L {
+    !calc_lt_gt_single(a, b).0
+}
+
+//scans from MSB to LSB, the first row where a column differs decides the result
+//returns (a < b, a > b)
+fn calc_lt_gt<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> (L, L) {
+    let mut lt = L::ZERO;
+    let mut gt = L::ZERO;
+    let mut eq = L::ALL_ONES;
+    for (a_i, b_i) in a.iter().zip(b) {
+        lt |= eq & !*a_i & *b_i;
+        gt |= eq & *a_i & !*b_i;
+        eq &= !(*a_i ^ *b_i);
+    }
+    (lt, gt)
+}
+
+fn calc_lt_gt_single<L: Lane, const N: usize>(a: &[L; N], b: u64) -> (L, L) {
+    if exceeds_rows::<N>(b) {
+        //b doesn't fit in N bits, so every column is smaller
+        return (L::ALL_ONES, L::ZERO);
+    }
+    let mut lt = L::ZERO;
+    let mut gt = L::ZERO;
+    let mut eq = L::ALL_ONES;
+    for (i, a_i) in a.iter().enumerate() {
+        if bit_mask::<L>(b, N - 1 - i) == L::ALL_ONES {
+            lt |= eq & !*a_i;
+            eq &= *a_i;
+        } else {
+            gt |= eq & *a_i;
+            eq &= !*a_i;
+        }
+    }
+    (lt, gt)
+}
+
+//picks a for every column where the bit in mask is set and b for every other column
+pub fn bitsliced_select<L: Lane, const N: usize>(mask: L, a: &[L; N], b: &[L; N]) -> [L; N] {
+    let mut out = *a;
+    bitsliced_select_inline(mask, &mut out, b);
+    out
+}
+
+//keeps the columns of a where the bit in mask is set, the other columns are replaced with b
+#[inline(always)]
+pub fn bitsliced_select_inline<L: Lane, const N: usize>(mask: L, a: &mut [L; N], b: &[L; N]) {
+    for (a_i, b_i) in a.iter_mut().zip(b) {
+        *a_i = (*a_i & mask) | (*b_i & !mask);
+    }
+}
+
+pub fn bitsliced_min<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> [L; N] {
+    bitsliced_select(bitsliced_lt(a, b), a, b)
+}
+
+pub fn bitsliced_max<L: Lane, const N: usize>(a: &[L; N], b: &[L; N]) -> [L; N] {
+    bitsliced_select(bitsliced_gt(a, b), a, b)
+}
+
+//shifts every column left by k bits, which moves row i + k to row i
+//shifting by N or more clears all columns
+pub fn bitsliced_shl<L: Lane, const N: usize>(a: &[L; N], k: usize) -> [L; N] {
+    let mut out = *a;
+    bitsliced_shl_inline(&mut out, k);
+    out
+}
+
+pub fn bitsliced_shl_inline<L: Lane, const N: usize>(a: &mut [L; N], k: usize) {
+    let k = k.min(N);
+    a.copy_within(k.., 0);
+    a[N - k..].fill(L::ZERO);
+}
+
+//shifts every column right by k bits, which moves row i to row i + k
+//shifting by N or more clears all columns
+pub fn bitsliced_shr<L: Lane, const N: usize>(a: &[L; N], k: usize) -> [L; N] {
+    let mut out = *a;
+    bitsliced_shr_inline(&mut out, k);
+    out
+}
+
+pub fn bitsliced_shr_inline<L: Lane, const N: usize>(a: &mut [L; N], k: usize) {
+    let k = k.min(N);
+    a.copy_within(..N - k, k);
+    a[..k].fill(L::ZERO);
+}
+
+//rotates every column left by k bits, k is taken modulo N
+pub fn bitsliced_rotl<L: Lane, const N: usize>(a: &[L; N], k: usize) -> [L; N] {
+    let mut out = *a;
+    bitsliced_rotl_inline(&mut out, k);
+    out
+}
+
+pub fn bitsliced_rotl_inline<L: Lane, const N: usize>(a: &mut [L; N], k: usize) {
+    //with no rows there is nothing to rotate (and k % N would divide by zero)
+    if N > 0 {
+        a.rotate_left(k % N);
+    }
+}
+
+//rotates every column right by k bits, k is taken modulo N
+pub fn bitsliced_rotr<L: Lane, const N: usize>(a: &[L; N], k: usize) -> [L; N] {
+    let mut out = *a;
+    bitsliced_rotr_inline(&mut out, k);
+    out
+}
+
+pub fn bitsliced_rotr_inline<L: Lane, const N: usize>(a: &mut [L; N], k: usize) {
+    if N > 0 {
+        a.rotate_right(k % N);
+    }
+}
+
+//variable shifts and rotations, every column is shifted by its own amount
+//amount is a bitsliced value with any number of rows (last row is LSB), e.g 6 rows for 0..64 with N = 64
+//works as a barrel shifter: stage j shifts by 2^j where bit j of the amount is set
+pub fn bitsliced_shl_var<L: Lane, const N: usize>(a: &[L; N], amount: &[L]) -> [L; N] {
+    let mut out = *a;
+    bitsliced_shl_var_inline(&mut out, amount);
+    out
+}
+
+pub fn bitsliced_shl_var_inline<L: Lane, const N: usize>(a: &mut [L; N], amount: &[L]) {
+    barrel_shift_inline(a, amount, bitsliced_shl_inline, false);
+}
+
+pub fn bitsliced_shr_var<L: Lane, const N: usize>(a: &[L; N], amount: &[L]) -> [L; N] {
+    let mut out = *a;
+    bitsliced_shr_var_inline(&mut out, amount);
+    out
+}
+
+pub fn bitsliced_shr_var_inline<L: Lane, const N: usize>(a: &mut [L; N], amount: &[L]) {
+    barrel_shift_inline(a, amount, bitsliced_shr_inline, false);
+}
+
+pub fn bitsliced_rotl_var<L: Lane, const N: usize>(a: &[L; N], amount: &[L]) -> [L; N] {
+    let mut out = *a;
+    bitsliced_rotl_var_inline(&mut out, amount);
+    out
+}
+
+pub fn bitsliced_rotl_var_inline<L: Lane, const N: usize>(a: &mut [L; N], amount: &[L]) {
+    barrel_shift_inline(a, amount, bitsliced_rotl_inline, true);
+}
+
+pub fn bitsliced_rotr_var<L: Lane, const N: usize>(a: &[L; N], amount: &[L]) -> [L; N] {
+    let mut out = *a;
+    bitsliced_rotr_var_inline(&mut out, amount);
+    out
+}
+
+pub fn bitsliced_rotr_var_inline<L: Lane, const N: usize>(a: &mut [L; N], amount: &[L]) {
+    barrel_shift_inline(a, amount, bitsliced_rotr_inline, true);
+}
+
+fn barrel_shift_inline<L: Lane, const N: usize>(
+    a: &mut [L; N],
+    amount: &[L],
+    shift: fn(&mut [L; N], usize),
+    rotate: bool,
+) {
+    if N == 0 {
+        return;
+    }
+    //2^j, reduced modulo N for rotations and capped at N for shifts (which clears everything)
+    let mut k = if rotate { 1 % N } else { 1.min(N) };
+    for mask in amount.iter().rev() {
+        let mut shifted = *a;
+        shift(&mut shifted, k);
+        bitsliced_select_inline(*mask, &mut shifted, a);
+        *a = shifted;
+        k = if rotate { (2 * k) % N } else { (2 * k).min(N) };
+    }
+}
+
+//this function only works when calculating the module with a number of the power of two
+//currently only supports a single modulo operation for all integers
+//example: if you want to calculate the modulo with 2^56, pass 56 to k
+pub fn bitsliced_modulo_power_of_two<L: Lane, const N: usize>(
+    a: &[L; N],
+    k: usize,
+) -> Result<[L; N], Error> {
+    check_modulo_power_of_two::<N>(k)?;
+    let mut out = [L::ZERO; N];
+    let start: usize = N - k;
+    out[start..].copy_from_slice(&a[start..]);
+
+    Ok(out)
+}
+
+#[inline(always)]
+pub fn bitsliced_modulo_power_of_two_inline<L: Lane, const N: usize>(
+    a: &mut [L; N],
+    k: usize,
+) -> Result<(), Error> {
+    check_modulo_power_of_two::<N>(k)?;
+    modulo_power_of_two_inline_unchecked(a, k);
+    Ok(())
+}
+
+//k must be <= N, see check_modulo_power_of_two
+#[inline(always)]
+pub(crate) fn modulo_power_of_two_inline_unchecked<L: Lane, const N: usize>(
+    a: &mut [L; N],
+    k: usize,
+) {
+    let end: usize = N - k;
+    a[..end].fill(L::ZERO);
+}
+
+//the argument checks of the modulo functions, so bulk callers can validate once up front
+pub(crate) fn check_modulo_power_of_two<const N: usize>(k: usize) -> Result<(), Error> {
+    if k > N {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("k must be <= {} for bitsliced modulo", N),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_modulo(m: u64) -> Result<(), Error> {
+    if m == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "m must be > 0 for bitsliced modulo",
+        ));
+    }
+    Ok(())
+}
+
+//calculates a % m for every column, works for any m > 0
+//uses binary long division: the remainder is shifted left bit by bit and m is
+//conditionally subtracted from every column where the remainder is >= m
+pub fn bitsliced_modulo<L: Lane, const N: usize>(a: &[L; N], m: u64) -> Result<[L; N], Error> {
+    let mut out = *a;
+    bitsliced_modulo_inline(&mut out, m)?;
+    Ok(out)
+}
+
+#[inline(always)]
+pub fn bitsliced_modulo_inline<L: Lane, const N: usize>(
+    a: &mut [L; N],
+    m: u64,
+) -> Result<(), Error> {
+    check_modulo(m)?;
+    modulo_inline_unchecked(a, m);
+    Ok(())
+}
+
+//m must be > 0, see check_modulo
+#[inline(always)]
+pub(crate) fn modulo_inline_unchecked<L: Lane, const N: usize>(a: &mut [L; N], m: u64) {
+    let bits = 64 - m.leading_zeros() as usize;
+    if bits > N {
+        //every column is smaller than m already
+        return;
+    }
+    if m.is_power_of_two() {
+        return modulo_power_of_two_inline_unchecked(a, m.trailing_zeros() as usize);
+    }
+    //the remainder is kept in place in a window of bits rows that moves down one row per step,
+    //after step p it holds the remainder of the first p + 1 bits and every row above it is zero
+    //this avoids copying the whole block for every bit
+    for p in bits - 1..N {
+        let low = p + 1 - bits;
+        //the window got shifted left by taking in row p, the bit shifted out is the row above it
+        let overflow = if low > 0 { a[low - 1] } else { L::ZERO };
+        let window = &mut a[low..=p];
+        //subtract m where the remainder is >= m, that is where it didn't borrow or has the extra bit
+        let keep = calc_borrow_single(window, m) & !overflow;
+        sub_single_masked_inline(window, m, !keep);
+        if low > 0 {
+            a[low - 1] = L::ZERO;
+        }
+    }
+}
+
+//borrow-out mask of rows - b, rows is a window of any length (last row is LSB)
+#[inline(always)]
+fn calc_borrow_single<L: Lane>(rows: &[L], b: u64) -> L {
+    let mut borrow = L::ZERO;
+    for (i, row) in rows.iter().enumerate().rev() {
+        let b_i = bit_mask(b, rows.len() - 1 - i);
+        borrow = calc_diff_borrow(*row, b_i, borrow).1;
+    }
+    borrow
+}
+
+//subtracts b from the columns of rows where the bit in mask is set, the other columns stay as they are
+#[inline(always)]
+fn sub_single_masked_inline<L: Lane>(rows: &mut [L], b: u64, mask: L) {
+    let len = rows.len();
+    let mut borrow = L::ZERO;
+    for (i, row) in rows.iter_mut().enumerate().rev() {
+        let b_i = bit_mask(b, len - 1 - i);
+        let res = calc_diff_borrow(*row, b_i, borrow);
+        *row = (res.0 & mask) | (*row & !mask);
+        borrow = res.1;
+    }
+}
+
+//reduction function: (H+I)%2^K
+//H=Hash,I=Index in chain,K=bits of the output, must be <= N
+pub fn reduction_power_of_two<L: Lane, const N: usize>(
+    h: &[L; N],
+    i: u64,
+    k: usize,
+) -> Result<[L; N], Error> {
+    check_modulo_power_of_two::<N>(k)?;
+    let mut sum = bitsliced_add_single(h, i);
+    modulo_power_of_two_inline_unchecked(&mut sum, k);
+    Ok(sum)
+}
+
+#[inline(always)]
+pub fn reduction_power_of_two_inline<L: Lane, const N: usize>(
+    h: &mut [L; N],
+    i: u64,
+    k: usize,
+) -> Result<(), Error> {
+    check_modulo_power_of_two::<N>(k)?;
+    bitsliced_add_single_inline(h, i);
+    modulo_power_of_two_inline_unchecked(h, k);
+    Ok(())
+}
+
+//reduction function: (H+I)%MAX_SIZE
+//H=Hash,I=Index in chain,MAX_SIZE=Max size of output in power of 2
+pub fn des_reduction<L: Lane>(h: &[L; 64], i: u64) -> [L; 64] {
+    reduction_power_of_two(h, i, 56).unwrap()
+}
+
+#[inline(always)]
+pub fn des_reduction_inline<L: Lane>(h: &mut [L; 64], i: u64) {
+    reduction_power_of_two_inline(h, i, 56).unwrap();
+}
+
+//reduction function: (H+I)%M
+//H=Hash,I=Index in chain,M=size of the keyspace, doesn't need to be a power of 2
+//H+I wraps around at 2^N before the modulo is applied
+pub fn reduction_mod<L: Lane, const N: usize>(h: &[L; N], i: u64, m: u64) -> Result<[L; N], Error> {
+    check_modulo(m)?;
+    let mut sum = bitsliced_add_single(h, i);
+    modulo_inline_unchecked(&mut sum, m);
+    Ok(sum)
+}
+
+#[inline(always)]
+pub fn reduction_mod_inline<L: Lane, const N: usize>(
+    h: &mut [L; N],
+    i: u64,
+    m: u64,
+) -> Result<(), Error> {
+    check_modulo(m)?;
+    bitsliced_add_single_inline(h, i);
+    modulo_inline_unchecked(h, m);
+    Ok(())
+}
+
+//reduction function: ((H+I)*n)>>N
+//H=Hash,I=Index in chain,n=size of the keyspace, output is in [0, n)
+pub fn fastrange_reduction<L: Lane, const N: usize>(h: &[L; N], i: u64, n: u64) -> [L; N] {
+    let sum = bitsliced_add_single(h, i);
+    bitsliced_fastrange(&sum, n)
+}
+
+#[inline(always)]
+pub fn fastrange_reduction_inline<L: Lane, const N: usize>(h: &mut [L; N], i: u64, n: u64) {
+    bitsliced_add_single_inline(h, i);
+    bitsliced_fastrange_inline(h, n);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransposeBackend {
+    Scalar,
+    Avx2,
+    Gfni,
+    Neon,
+}
+
+impl TransposeBackend {
+    //in order of preference
+    pub const ALL: [TransposeBackend; 4] = [
+        TransposeBackend::Gfni,
+        TransposeBackend::Avx2,
+        TransposeBackend::Neon,
+        TransposeBackend::Scalar,
+    ];
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            TransposeBackend::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            TransposeBackend::Avx2 => std::is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "x86_64")]
+            TransposeBackend::Gfni => {
+                std::is_x86_feature_detected!("gfni")
+                    && std::is_x86_feature_detected!("avx512f")
+                    && std::is_x86_feature_detected!("avx512bw")
+                    && std::is_x86_feature_detected!("avx512vbmi")
+            }
+            #[cfg(target_arch = "aarch64")]
+            TransposeBackend::Neon => true,
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for TransposeBackend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "scalar" => Ok(TransposeBackend::Scalar),
+            "avx2" => Ok(TransposeBackend::Avx2),
+            "gfni" => Ok(TransposeBackend::Gfni),
+            "neon" => Ok(TransposeBackend::Neon),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown transpose backend '{}'", s),
+            )),
+        }
+    }
+}
+
+//set this environment variable to pin the backend transpose_64x64 uses, e.g BITSLICED_OP_TRANSPOSE=avx2
+pub const TRANSPOSE_BACKEND_ENV: &str = "BITSLICED_OP_TRANSPOSE";
+
+static TRANSPOSE_BACKEND: OnceLock<TransposeBackend> = OnceLock::new();
+
+//backend transpose_64x64 uses: the environment override if set, else the fastest one the cpu supports
+//if the override names an unknown backend or one the cpu doesn't support, a warning is printed once
+//and the fastest supported backend is used instead, see try_transpose_backend to get the error
+pub fn transpose_backend() -> TransposeBackend {
+    *TRANSPOSE_BACKEND.get_or_init(|| {
+        try_transpose_backend().unwrap_or_else(|e| {
+            eprintln!("warning: ignoring {}: {}", TRANSPOSE_BACKEND_ENV, e);
+            fastest_transpose_backend()
+        })
+    })
+}
+
+//same as transpose_backend, but fails instead of falling back if the environment override is invalid
+pub fn try_transpose_backend() -> Result<TransposeBackend, Error> {
+    match std::env::var(TRANSPOSE_BACKEND_ENV) {
+        Ok(name) => {
+            let backend = name.parse::<TransposeBackend>()?;
+            check_backend(backend)?;
+            Ok(backend)
+        }
+        Err(_) => Ok(fastest_transpose_backend()),
+    }
+}
+
+fn fastest_transpose_backend() -> TransposeBackend {
+    TransposeBackend::ALL
+        .into_iter()
+        .find(|backend| backend.is_supported())
+        .unwrap_or(TransposeBackend::Scalar)
+}
+
+//transpose 64x64 bit matrix with the backend returned by transpose_backend
+pub fn transpose_64x64(input: &[u64; 64]) -> [u64; 64] {
+    unsafe { transpose_64x64_unchecked(transpose_backend(), input) }
+}
+
+//transpose 64x64 bit matrix with a specific backend, fails if the cpu doesn't support it
+pub fn transpose_64x64_with(
+    backend: TransposeBackend,
+    input: &[u64; 64],
+) -> Result<[u64; 64], Error> {
+    check_backend(backend)?;
+    Ok(unsafe { transpose_64x64_unchecked(backend, input) })
+}
+
+//transpose 64x64 bit matrix in place with the backend returned by transpose_backend
+pub fn transpose_64x64_inplace(matrix: &mut [u64; 64]) {
+    unsafe { transpose_64x64_inplace_unchecked(transpose_backend(), matrix) }
+}
+
+//transpose 64x64 bit matrix in place with a specific backend, fails if the cpu doesn't support it
+pub fn transpose_64x64_inplace_with(
+    backend: TransposeBackend,
+    matrix: &mut [u64; 64],
+) -> Result<(), Error> {
+    check_backend(backend)?;
+    unsafe { transpose_64x64_inplace_unchecked(backend, matrix) };
+    Ok(())
+}
+
+#[inline(always)]
+unsafe fn transpose_64x64_inplace_unchecked(backend: TransposeBackend, matrix: &mut [u64; 64]) {
+    match backend {
+        #[cfg(target_arch = "x86_64")]
+        TransposeBackend::Gfni => unsafe { crate::transpose::transpose_gfni_inplace(matrix) },
+        #[cfg(target_arch = "x86_64")]
+        TransposeBackend::Avx2 => unsafe { crate::transpose::transpose_avx2_inplace(matrix) },
+        #[cfg(target_arch = "aarch64")]
+        TransposeBackend::Neon => crate::transpose::transpose_neon_inplace(matrix),
+        _ => crate::transpose::transpose_scalar_inplace(matrix),
+    }
+}
+
+//transpose every matrix of input into output, the backend is picked once for the whole batch
+pub fn transpose_many(input: &[[u64; 64]], output: &mut [[u64; 64]]) {
+    assert_eq!(
+        input.len(),
+        output.len(),
+        "input and output must hold the same number of matrices"
+    );
+    let backend = transpose_backend();
+    for (matrix, transposed) in input.iter().zip(output.iter_mut()) {
+        *transposed = unsafe { transpose_64x64_unchecked(backend, matrix) };
+    }
+}
+
+//smaller batches aren't worth starting a thread for
+const MIN_MATRICES_PER_THREAD: usize = 4096;
+
+//same as transpose_many, split over all cores
+pub fn transpose_many_parallel(input: &[[u64; 64]], output: &mut [[u64; 64]]) {
+    assert_eq!(
+        input.len(),
+        output.len(),
+        "input and output must hold the same number of matrices"
+    );
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = input.len().div_ceil(threads).max(MIN_MATRICES_PER_THREAD);
+    if chunk_size >= input.len() {
+        //a single chunk, spawning a thread for it would only add overhead
+        return transpose_many(input, output);
+    }
+    std::thread::scope(|s| {
+        for (input, output) in input.chunks(chunk_size).zip(output.chunks_mut(chunk_size)) {
+            s.spawn(move || transpose_many(input, output));
+        }
+    });
+}
+
+fn check_backend(backend: TransposeBackend) -> Result<(), Error> {
+    if !backend.is_supported() {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("{:?} transpose is not supported on this cpu", backend),
+        ));
+    }
+    Ok(())
+}
+
+/// # Safety
+/// The cpu must support the backend, see `TransposeBackend::is_supported`. `transpose_backend` only
+/// returns supported backends and the `_with` functions check it with `check_backend`.
+/// Every other `_unchecked` transpose in this file has the same requirement.
+#[inline(always)]
+unsafe fn transpose_64x64_unchecked(backend: TransposeBackend, input: &[u64; 64]) -> [u64; 64] {
+    match backend {
+        #[cfg(target_arch = "x86_64")]
+        TransposeBackend::Gfni => unsafe { crate::transpose::transpose_gfni(input) },
+        #[cfg(target_arch = "x86_64")]
+        TransposeBackend::Avx2 => unsafe { crate::transpose::transpose_avx2(input) },
+        #[cfg(target_arch = "aarch64")]
+        TransposeBackend::Neon => crate::transpose::transpose_neon(input),
+        _ => crate::transpose::transpose_scalar(input),
+    }
+}
+
+//the other shapes use the same backends: rows are integers, column c of a row is bit width - 1 - c
+
+//transpose 8x8 bit matrix
+pub fn transpose_8x8(input: &[u8; 8]) -> [u8; 8] {
+    unsafe { transpose_8x8_unchecked(transpose_backend(), input) }
+}
+
+pub fn transpose_8x8_with(backend: TransposeBackend, input: &[u8; 8]) -> Result<[u8; 8], Error> {
+    check_backend(backend)?;
+    Ok(unsafe { transpose_8x8_unchecked(backend, input) })
+}
+
+#[inline(always)]
+unsafe fn transpose_8x8_unchecked(backend: TransposeBackend, input: &[u8; 8]) -> [u8; 8] {
+    match backend {
+        #[cfg(target_arch = "x86_64")]
+        TransposeBackend::Gfni => unsafe { crate::transpose::transpose_8x8_gfni(input) },
+        //a few u8 operations, vector registers don't pay off for a single block
+        _ => crate::transpose::transpose_8x8_scalar(input),
+    }
+}
+
+//transpose 32x32 bit matrix
+pub fn transpose_32x32(input: &[u32; 32]) -> [u32; 32] {
+    unsafe { transpose_32x32_unchecked(transpose_backend(), input) }
+}
+
+pub fn transpose_32x32_with(
+    backend: TransposeBackend,
+    input: &[u32; 32],
+) -> Result<[u32; 32], Error> {
+    check_backend(backend)?;
+    Ok(unsafe { transpose_32x32_unchecked(backend, input) })
+}
+
+#[inline(always)]
+unsafe fn transpose_32x32_unchecked(backend: TransposeBackend, input: &[u32; 32]) -> [u32; 32] {
+    match backend {
+        TransposeBackend::Scalar => crate::transpose::transpose_32x32_scalar(input),
+        //the top left quarter of a 64x64 matrix, the simd backends are still faster than 32-bit scalar
+        _ => {
+            let mut matrix = [0u64; 64];
+            for (row, bits) in matrix.iter_mut().zip(input) {
+                *row = (*bits as u64) << 32;
+            }
+            let transposed = unsafe { transpose_64x64_unchecked(backend, &matrix) };
+            std::array::from_fn(|i| (transposed[i] >> 32) as u32)
+        }
+    }
+}
+
+//transpose 128x128 bit matrix
+pub fn transpose_128x128(input: &[u128; 128]) -> [u128; 128] {
+    unsafe { transpose_128x128_unchecked(transpose_backend(), input) }
+}
+
+pub fn transpose_128x128_with(
+    backend: TransposeBackend,
+    input: &[u128; 128],
+) -> Result<[u128; 128], Error> {
+    check_backend(backend)?;
+    Ok(unsafe { transpose_128x128_unchecked(backend, input) })
+}
+
+#[inline(always)]
+unsafe fn transpose_128x128_unchecked(
+    backend: TransposeBackend,
+    input: &[u128; 128],
+) -> [u128; 128] {
+    //transpose the four 64x64 blocks and swap the top right and bottom left one
+    let (top, bottom) = input.split_at(64);
+    let block = |rows: &[u128], shift: u32| -> [u64; 64] {
+        let matrix = std::array::from_fn(|i| (rows[i] >> shift) as u64);
+        unsafe { transpose_64x64_unchecked(backend, &matrix) }
+    };
+    let (top_left, top_right) = (block(top, 64), block(top, 0));
+    let (bottom_left, bottom_right) = (block(bottom, 64), block(bottom, 0));
+    std::array::from_fn(|i| {
+        if i < 64 {
+            (top_left[i] as u128) << 64 | bottom_left[i] as u128
+        } else {
+            (top_right[i - 64] as u128) << 64 | bottom_right[i - 64] as u128
+        }
+    })
+}
+
+//64 integers of N bits (the low N bits of every u64) to N bitsliced rows of 64 columns, last row is LSB
+//e.g 56 rows for DES keys
+pub fn transpose_64xn<const N: usize>(input: &[u64; 64]) -> [u64; N] {
+    unsafe { transpose_64xn_unchecked(transpose_backend(), input) }
+}
+
+pub fn transpose_64xn_with<const N: usize>(
+    backend: TransposeBackend,
+    input: &[u64; 64],
+) -> Result<[u64; N], Error> {
+    check_backend(backend)?;
+    Ok(unsafe { transpose_64xn_unchecked(backend, input) })
 }
 
-pub fn bitsliced_add_inline(a: &mut [u64x8; 64], b: &[u64x8; 64]) {
-    let mut carry = u64x8::ZERO;
-    for i in (0..64).rev() {
-        let res = calc_sum_carry(a[i], b[i], carry);
-        a[i] = res.0;
-        //only set carry if we haven't reached the end yet, we currently ignore overflows
-        carry = res.1;
-    }
+#[inline(always)]
+unsafe fn transpose_64xn_unchecked<const N: usize>(
+    backend: TransposeBackend,
+    input: &[u64; 64],
+) -> [u64; N] {
+    const { assert!(N <= 64, "N must be <= 64") };
+    //the low N bits are the last N columns, so they end up in the last N rows
+    let transposed = unsafe { transpose_64x64_unchecked(backend, input) };
+    transposed[64 - N..].try_into().unwrap()
 }
 
-pub fn bitsliced_add_single_inline(a: &mut [u64x8; 64], b: u64) {
-    let mut carry = u64x8::ZERO;
-    for i in (0..64).rev() {
-        let shift_right = 63 - i;
-        let current_bit = (b >> shift_right) & 1;
-        let b_i = if current_bit == 1 { ALL_ONES } else { ZERO };
-        let res = calc_sum_carry(a[i], b_i, carry);
-        a[i] = res.0;
-        //only set carry if we haven't reached the end yet, we currently ignore overflows
-        carry = res.1;
+//inverse of transpose_64xn: N bitsliced rows of 64 columns to 64 integers of N bits
+pub fn transpose_nx64<const N: usize>(input: &[u64; N]) -> [u64; 64] {
+    unsafe { transpose_nx64_unchecked(transpose_backend(), input) }
+}
+
+pub fn transpose_nx64_with<const N: usize>(
+    backend: TransposeBackend,
+    input: &[u64; N],
+) -> Result<[u64; 64], Error> {
+    check_backend(backend)?;
+    Ok(unsafe { transpose_nx64_unchecked(backend, input) })
+}
+
+#[inline(always)]
+unsafe fn transpose_nx64_unchecked<const N: usize>(
+    backend: TransposeBackend,
+    input: &[u64; N],
+) -> [u64; 64] {
+    const { assert!(N <= 64, "N must be <= 64") };
+    let mut matrix = [0u64; 64];
+    matrix[64 - N..].copy_from_slice(input);
+    unsafe { transpose_64x64_unchecked(backend, &matrix) }
+}
+
+//pack 512 integers into bitsliced form: integer k becomes column k, i.e lane k / 64, bit 63 - k % 64
+//row i holds bit 63 - i of every integer (last row is LSB)
+pub fn to_bitsliced(input: &[u64; 512]) -> [u64x8; 64] {
+    #[cfg(target_arch = "x86_64")]
+    if transpose_backend() == TransposeBackend::Gfni {
+        return unsafe { crate::transpose::transpose_gfni_x8(input) };
+    }
+    let mut rows = [[0u64; 8]; 64];
+    for (j, matrix) in input.chunks_exact(64).enumerate() {
+        let transposed = transpose_64x64(matrix.try_into().unwrap());
+        for (row, bits) in rows.iter_mut().zip(transposed) {
+            row[j] = bits;
+        }
     }
+    rows.map(u64x8::new)
 }
 
-fn calc_sum_carry(a: u64x8, b: u64x8, carry: u64x8) -> (u64x8, u64x8) {
-    let sum = a ^ b ^ carry;
-    let next_carry = (a & b) | (carry & (a ^ b));
-    (sum, next_carry)
+//inverse of to_bitsliced
+pub fn from_bitsliced(input: &[u64x8; 64]) -> [u64; 512] {
+    let mut output = [0u64; 512];
+    for (j, integers) in output.chunks_exact_mut(64).enumerate() {
+        let matrix = input.map(|row| row.to_array()[j]);
+        integers.copy_from_slice(&transpose_64x64(&matrix));
+    }
+    output
 }
 
-//this function only works when calculating the module with a number of the power of two
-//currently only supports a single modulo operation for all integers
-//example: if you want to calculate the modulo with 2^56, pass 56 to k
-pub fn bitsliced_modulo_power_of_two(a: &[u64x8; 64], k: usize) -> Result<[u64x8; 64], Error> {
-    if k > 64 {
+//pack up to 512 byte strings of at most ROWS / 8 bytes into bitsliced form, string k becomes column k
+//every string is read as one big endian integer: row 8b + i holds bit 7 - i of byte b (last row is LSB of the last byte)
+//shorter strings are padded with zero bytes at the end
+pub fn strings_to_bitsliced<const ROWS: usize>(strings: &[&[u8]]) -> Result<[u64x8; ROWS], Error> {
+    const { assert!(ROWS.is_multiple_of(8), "ROWS must be a multiple of 8") };
+    if strings.len() > 512 {
         return Err(Error::new(
             ErrorKind::InvalidData,
-            "k must be <= 64 for bitsliced modulo",
+            format!("at most 512 strings fit in a block, got {}", strings.len()),
+        ));
+    }
+    let max_len = ROWS / 8;
+    if let Some(s) = strings.iter().find(|s| s.len() > max_len) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("strings must be <= {} bytes, got {}", max_len, s.len()),
         ));
     }
-    let mut out = [u64x8::splat(0); 64];
-    let start: usize = 64 - k;
-    out[start..].copy_from_slice(&a[start..]);
 
-    Ok(out)
+    let mut output = [ZERO; ROWS];
+    //every 8 bytes of the strings are one 64 row transpose
+    for (c, rows) in output.chunks_mut(64).enumerate() {
+        let bytes = rows.len() / 8;
+        let mut integers = [0u64; 512];
+        for (integer, string) in integers.iter_mut().zip(strings) {
+            let mut be = [0u8; 8];
+            let chunk = string.get(8 * c..).unwrap_or_default();
+            let n = chunk.len().min(bytes);
+            be[8 - bytes..8 - bytes + n].copy_from_slice(&chunk[..n]);
+            *integer = u64::from_be_bytes(be);
+        }
+        rows.copy_from_slice(&to_bitsliced(&integers)[64 - rows.len()..]);
+    }
+    Ok(output)
 }
 
-pub fn bitsliced_modulo_power_of_two_inline(a: &mut [u64x8; 64], k: usize) -> Result<(), Error> {
-    if k > 64 {
+//inverse of strings_to_bitsliced, string k is cut to lengths[k] bytes
+//the lengths are needed because the zero padding can't be told apart from strings that end in zero bytes
+pub fn bitsliced_to_strings<const ROWS: usize>(
+    input: &[u64x8; ROWS],
+    lengths: &[usize],
+) -> Result<Vec<Vec<u8>>, Error> {
+    const { assert!(ROWS.is_multiple_of(8), "ROWS must be a multiple of 8") };
+    if lengths.len() > 512 {
         return Err(Error::new(
             ErrorKind::InvalidData,
-            "k must be <= 64 for bitsliced modulo",
+            format!("a block holds at most 512 strings, got {}", lengths.len()),
         ));
     }
-    let end: usize = 64 - k;
-    for i in 0..end {
-        a[i] = u64x8::splat(0);
+    let max_len = ROWS / 8;
+    if let Some(len) = lengths.iter().find(|len| **len > max_len) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("strings must be <= {} bytes, got {}", max_len, len),
+        ));
     }
-
-    Ok(())
-}
-
-//reduction function: (H+I)%MAX_SIZE
-//H=Hash,I=Index in chain,MAX_SIZE=Max size of output in power of 2
-pub fn des_reduction(h: &[u64x8; 64], i: u64) -> [u64x8; 64] {
-    let mut sum = bitsliced_add_single(h, i);
-    bitsliced_modulo_power_of_two_inline(&mut sum, 56).unwrap();
-    sum
-}
-
-pub fn des_reduction_inline(h: &mut [u64x8; 64], i: u64) {
-    bitsliced_add_single_inline(h, i);
-    bitsliced_modulo_power_of_two_inline(h, 56).unwrap();
-}
-
-static USE_GFNI: OnceLock<bool> = OnceLock::new();
-
-//transpose 64x64 bit matrix
-//use gfni if the cpu supports it, fallback to scalar if it doesn't
-pub fn transpose_64x64(input: &[u64; 64]) -> [u64; 64] {
-    if *USE_GFNI.get_or_init(|| {
-        #[cfg(target_arch = "x86_64")]
-        {
-            std::is_x86_feature_detected!("gfni")
-                && std::is_x86_feature_detected!("avx512f")
-                && std::is_x86_feature_detected!("avx512bw")
-                && std::is_x86_feature_detected!("avx512vbmi")
-        }
-        #[cfg(not(target_arch = "x86_64"))]
-        {
-            false
+    let mut strings: Vec<Vec<u8>> = lengths
+        .iter()
+        .map(|_| Vec::with_capacity(max_len))
+        .collect();
+    for rows in input.chunks(64) {
+        let bytes = rows.len() / 8;
+        let mut matrix = [ZERO; 64];
+        matrix[64 - rows.len()..].copy_from_slice(rows);
+        for (string, integer) in strings.iter_mut().zip(from_bitsliced(&matrix)) {
+            string.extend_from_slice(&integer.to_be_bytes()[8 - bytes..]);
         }
-    }) {
-        unsafe { crate::transpose::transpose_gfni(input) }
-    } else {
-        transpose_scalar(input)
     }
+    for (string, len) in strings.iter_mut().zip(lengths) {
+        string.truncate(*len);
+    }
+    Ok(strings)
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_range_loop, clippy::let_unit_value)]
 mod tests {
     use super::*;
+    use crate::test_util::random_matrices;
 
     #[test]
     fn test_add_works() {
@@ -200,6 +1287,612 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_add_checked_works() {
+        //columns in the first half of every lane hold u64::MAX, the others 0
+        let a = [splat(0xFFFFFFFF00000000); 64];
+        let mut b = [ZERO; 64];
+        b[63] = ALL_ONES;
+        let (sum, carry) = bitsliced_add_checked(&a, &b);
+        assert_eq!(carry, splat(0xFFFFFFFF00000000));
+        //u64::MAX + 1 wraps around to 0, 0 + 1 = 1
+        assert_eq!(sum[63], splat(0x00000000FFFFFFFF));
+        for row in &sum[..63] {
+            assert_eq!(*row, ZERO);
+        }
+
+        let (single, carry) = bitsliced_add_single_checked(&a, 1);
+        assert_eq!(single, sum);
+        assert_eq!(carry, splat(0xFFFFFFFF00000000));
+
+        let mut c = a;
+        assert_eq!(bitsliced_add_checked_inline(&mut c, &b), carry);
+        assert_eq!(c, sum);
+        let mut c = a;
+        assert_eq!(bitsliced_add_single_checked_inline(&mut c, 1), carry);
+        assert_eq!(c, sum);
+        let mut c = a;
+        assert_eq!(bitsliced_add_single_checked_inline(&mut c, 0), ZERO);
+        assert_eq!(c, a);
+    }
+
+    #[test]
+    fn test_add_saturating_works() {
+        let a = [splat(0xFFFFFFFF00000000); 64];
+        let mut b = [ZERO; 64];
+        b[63] = ALL_ONES;
+        let sum = bitsliced_add_saturating(&a, &b);
+        //overflowed columns stay at u64::MAX, the others hold 1
+        assert_eq!(sum[63], ALL_ONES);
+        for row in &sum[..63] {
+            assert_eq!(*row, splat(0xFFFFFFFF00000000));
+        }
+        assert_eq!(bitsliced_add_single_saturating(&a, 1), sum);
+
+        let mut c = a;
+        bitsliced_add_saturating_inline(&mut c, &b);
+        assert_eq!(c, sum);
+        let mut c = a;
+        bitsliced_add_single_saturating_inline(&mut c, 1);
+        assert_eq!(c, sum);
+    }
+
+    #[test]
+    fn test_add_wrapping_works() {
+        let a = [splat(0xFFFFFFFF00000000); 64];
+        let mut b = [ZERO; 64];
+        b[63] = ALL_ONES;
+        let sum = bitsliced_add_wrapping(&a, &b);
+        assert_eq!(sum, bitsliced_add_checked(&a, &b).0);
+        assert_eq!(bitsliced_add_single_wrapping(&a, 1), sum);
+
+        let mut c = a;
+        bitsliced_add_wrapping_inline(&mut c, &b);
+        assert_eq!(c, sum);
+        let mut c = a;
+        bitsliced_add_single_wrapping_inline(&mut c, 1);
+        assert_eq!(c, sum);
+    }
+
+    #[test]
+    fn test_sub_works() {
+        let mut a = [ZERO; 64];
+        a[62] = ALL_ONES;
+        let mut b = [ZERO; 64];
+        b[63] = ALL_ONES;
+        let diff = bitsliced_sub(&a, &b);
+        assert_eq!(diff[63], ALL_ONES);
+        for row in &diff[..63] {
+            assert_eq!(*row, ZERO);
+        }
+    }
+
+    #[test]
+    fn test_sub_single_works() {
+        let mut a = [ZERO; 64];
+        a[62] = ALL_ONES;
+        let diff = bitsliced_sub_single(&a, 1);
+        assert_eq!(diff[63], ALL_ONES);
+        for row in &diff[..63] {
+            assert_eq!(*row, ZERO);
+        }
+    }
+
+    #[test]
+    fn test_sub_inline_works() {
+        let mut a = [ZERO; 64];
+        a[62] = ALL_ONES;
+        let mut b = [ZERO; 64];
+        b[63] = ALL_ONES;
+        bitsliced_sub_inline(&mut a, &b);
+        assert_eq!(a[63], ALL_ONES);
+        for row in &a[..63] {
+            assert_eq!(*row, ZERO);
+        }
+    }
+
+    #[test]
+    fn test_sub_single_inline_works() {
+        let mut a = [ZERO; 64];
+        a[62] = ALL_ONES;
+        bitsliced_sub_single_inline(&mut a, 1);
+        assert_eq!(a[63], ALL_ONES);
+        for row in &a[..63] {
+            assert_eq!(*row, ZERO);
+        }
+    }
+
+    #[test]
+    fn test_sub_borrow_works() {
+        //columns in the first half of every lane hold 1, the others 0
+        let mut a = [ZERO; 64];
+        a[63] = splat(0xFFFFFFFF00000000);
+        let mut b = [ZERO; 64];
+        b[63] = ALL_ONES;
+        let (diff, borrow) = bitsliced_sub_with_borrow(&a, &b);
+        assert_eq!(borrow, splat(0x00000000FFFFFFFF));
+        //0 - 1 wraps around to all ones
+        for row in diff {
+            assert_eq!(row, splat(0x00000000FFFFFFFF));
+        }
+
+        let (_, borrow) = bitsliced_sub_single_with_borrow(&a, 1);
+        assert_eq!(borrow, splat(0x00000000FFFFFFFF));
+
+        let borrow = bitsliced_sub_single_with_borrow_inline(&mut a, 0);
+        assert_eq!(borrow, ZERO);
+        let borrow = bitsliced_sub_with_borrow_inline(&mut a, &b);
+        assert_eq!(borrow, splat(0x00000000FFFFFFFF));
+    }
+
+    //every column holds n
+    fn broadcast(n: u64) -> [u64x8; 64] {
+        let mut out = [ZERO; 64];
+        for (i, row) in out.iter_mut().enumerate() {
+            if (n >> (63 - i)) & 1 == 1 {
+                *row = ALL_ONES;
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_mul_single_works() {
+        let a = broadcast(3);
+        assert_eq!(bitsliced_mul_single(&a, 5), broadcast(15));
+        assert_eq!(bitsliced_mul_single(&a, 0), broadcast(0));
+        //only the lower 64 bits are kept
+        let a = broadcast(0x8000000000000001);
+        assert_eq!(bitsliced_mul_single(&a, 2), broadcast(2));
+        let a = broadcast(0x0123456789ABCDEF);
+        assert_eq!(
+            bitsliced_mul_single(&a, 0x9E3779B97F4A7C15),
+            broadcast(0x0123456789ABCDEFu64.wrapping_mul(0x9E3779B97F4A7C15))
+        );
+    }
+
+    #[test]
+    fn test_mul_single_inline_works() {
+        let mut a = broadcast(7);
+        bitsliced_mul_single_inline(&mut a, 6);
+        assert_eq!(a, broadcast(42));
+    }
+
+    #[test]
+    fn test_mul_works() {
+        let (low, high) = bitsliced_mul(&broadcast(6), &broadcast(7));
+        assert_eq!(low, broadcast(42));
+        assert_eq!(high, broadcast(0));
+
+        let (low, high) = bitsliced_mul(&broadcast(u64::MAX), &broadcast(u64::MAX));
+        assert_eq!(low, broadcast(1));
+        assert_eq!(high, broadcast(0xFFFFFFFFFFFFFFFE));
+
+        let x = 0x0123456789ABCDEFu64;
+        let y = 0xFEDCBA9876543210u64;
+        let expected = x as u128 * y as u128;
+        let (low, high) = bitsliced_mul(&broadcast(x), &broadcast(y));
+        assert_eq!(low, broadcast(expected as u64));
+        assert_eq!(high, broadcast((expected >> 64) as u64));
+    }
+
+    #[test]
+    fn test_mul_per_column_works() {
+        //columns in the first half of every lane get multiplied by 3, the others by 0
+        let a = broadcast(u64::MAX);
+        let mut b = [ZERO; 64];
+        b[62] = splat(0xFFFFFFFF00000000);
+        b[63] = splat(0xFFFFFFFF00000000);
+        let (low, high) = bitsliced_mul(&a, &b);
+        //u64::MAX * 3 = 2 * 2^64 + (2^64 - 3)
+        let expected_low = broadcast(u64::MAX - 2);
+        let expected_high = broadcast(2);
+        for i in 0..64 {
+            assert_eq!(low[i], expected_low[i] & splat(0xFFFFFFFF00000000));
+            assert_eq!(high[i], expected_high[i] & splat(0xFFFFFFFF00000000));
+        }
+    }
+
+    #[test]
+    fn test_modulo_arbitrary_works() {
+        for (n, m) in [
+            (0u64, 3u64),
+            (100, 7),
+            (u64::MAX, 3),
+            (u64::MAX, u64::MAX),
+            (u64::MAX - 1, u64::MAX),
+            (0x0123456789ABCDEF, 26u64.pow(7)),
+            (0xFEDCBA9876543210, 95u64.pow(8)),
+            (0xFEDCBA9876543210, 1),
+            (0xFEDCBA9876543210, 1 << 40),
+            (0xFEDCBA9876543210, (1 << 63) + 1),
+        ] {
+            let res = bitsliced_modulo(&broadcast(n), m).unwrap();
+            assert_eq!(res, broadcast(n % m), "{} % {}", n, m);
+        }
+    }
+
+    #[test]
+    fn test_modulo_arbitrary_per_column_works() {
+        //columns in the first half of every lane hold 10, the others 4
+        let mut a = [ZERO; 64];
+        a[60] = splat(0xFFFFFFFF00000000);
+        a[61] = splat(0x00000000FFFFFFFF);
+        a[62] = splat(0xFFFFFFFF00000000);
+        bitsliced_modulo_inline(&mut a, 6).unwrap();
+        //10 % 6 = 4 and 4 % 6 = 4
+        assert_eq!(a, broadcast(4));
+        let mut a = [ZERO; 64];
+        a[60] = splat(0xFFFFFFFF00000000);
+        a[61] = splat(0x00000000FFFFFFFF);
+        a[62] = splat(0xFFFFFFFF00000000);
+        bitsliced_modulo_inline(&mut a, 7).unwrap();
+        //10 % 7 = 3 and 4 % 7 = 4
+        assert_eq!(a[61], splat(0x00000000FFFFFFFF));
+        assert_eq!(a[62], splat(0xFFFFFFFF00000000));
+        assert_eq!(a[63], splat(0xFFFFFFFF00000000));
+    }
+
+    #[test]
+    fn test_modulo_zero_fails() {
+        assert!(bitsliced_modulo(&broadcast(1), 0).is_err());
+    }
+
+    #[test]
+    fn test_reduction_mod_works() {
+        let keyspace = 26u64.pow(7);
+        let h = broadcast(0xFEDCBA9876543210);
+        let expected = broadcast(0xFEDCBA9876543210u64.wrapping_add(42) % keyspace);
+        assert_eq!(reduction_mod(&h, 42, keyspace).unwrap(), expected);
+        let mut h = h;
+        reduction_mod_inline(&mut h, 42, keyspace).unwrap();
+        assert_eq!(h, expected);
+    }
+
+    #[test]
+    fn test_fastrange_works() {
+        for (x, n) in [
+            (0u64, 10u64),
+            (u64::MAX, 10),
+            (u64::MAX, u64::MAX),
+            (1 << 63, 26u64.pow(7)),
+            (0xFEDCBA9876543210, 95u64.pow(8)),
+            (0xFEDCBA9876543210, 0),
+        ] {
+            let expected = ((x as u128 * n as u128) >> 64) as u64;
+            assert_eq!(bitsliced_fastrange(&broadcast(x), n), broadcast(expected));
+            let mut a = broadcast(x);
+            bitsliced_fastrange_inline(&mut a, n);
+            assert_eq!(a, broadcast(expected));
+        }
+    }
+
+    #[test]
+    fn test_fastrange_reduction_works() {
+        let keyspace = 26u64.pow(7);
+        let h = broadcast(0xFEDCBA9876543210);
+        let sum = 0xFEDCBA9876543210u64.wrapping_add(42);
+        let expected = broadcast(((sum as u128 * keyspace as u128) >> 64) as u64);
+        assert_eq!(fastrange_reduction(&h, 42, keyspace), expected);
+        let mut h = h;
+        fastrange_reduction_inline(&mut h, 42, keyspace);
+        assert_eq!(h, expected);
+    }
+
+    #[test]
+    fn test_compare_works() {
+        for (x, y) in [
+            (0u64, 0u64),
+            (0, 1),
+            (1, 0),
+            (u64::MAX, u64::MAX),
+            (u64::MAX - 1, u64::MAX),
+            (1 << 63, (1 << 63) - 1),
+            (0x0123456789ABCDEF, 0x0123456789ABCDEE),
+        ] {
+            let mask = |c: bool| if c { ALL_ONES } else { ZERO };
+            let (a, b) = (broadcast(x), broadcast(y));
+            assert_eq!(bitsliced_eq(&a, &b), mask(x == y), "{} == {}", x, y);
+            assert_eq!(bitsliced_lt(&a, &b), mask(x < y), "{} < {}", x, y);
+            assert_eq!(bitsliced_le(&a, &b), mask(x <= y), "{} <= {}", x, y);
+            assert_eq!(bitsliced_gt(&a, &b), mask(x > y), "{} > {}", x, y);
+            assert_eq!(bitsliced_ge(&a, &b), mask(x >= y), "{} >= {}", x, y);
+            assert_eq!(bitsliced_eq_single(&a, y), mask(x == y), "{} == {}", x, y);
+            assert_eq!(bitsliced_lt_single(&a, y), mask(x < y), "{} < {}", x, y);
+            assert_eq!(bitsliced_le_single(&a, y), mask(x <= y), "{} <= {}", x, y);
+            assert_eq!(bitsliced_gt_single(&a, y), mask(x > y), "{} > {}", x, y);
+            assert_eq!(bitsliced_ge_single(&a, y), mask(x >= y), "{} >= {}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_compare_per_column_works() {
+        //columns in the first half of every lane hold 2, the others 1
+        let mut a = [ZERO; 64];
+        a[62] = splat(0xFFFFFFFF00000000);
+        a[63] = splat(0x00000000FFFFFFFF);
+        let b = broadcast(1);
+        assert_eq!(bitsliced_eq(&a, &b), splat(0x00000000FFFFFFFF));
+        assert_eq!(bitsliced_gt(&a, &b), splat(0xFFFFFFFF00000000));
+        assert_eq!(bitsliced_lt(&a, &b), ZERO);
+        assert_eq!(bitsliced_eq_single(&a, 2), splat(0xFFFFFFFF00000000));
+        assert_eq!(bitsliced_lt_single(&a, 2), splat(0x00000000FFFFFFFF));
+        assert_eq!(bitsliced_ge_single(&a, 2), splat(0xFFFFFFFF00000000));
+    }
+
+    #[test]
+    fn test_select_works() {
+        let a = broadcast(1);
+        let b = broadcast(2);
+        let mask = splat(0xFFFFFFFF00000000);
+        let selected = bitsliced_select(mask, &a, &b);
+        assert_eq!(selected[62], splat(0x00000000FFFFFFFF));
+        assert_eq!(selected[63], splat(0xFFFFFFFF00000000));
+        assert_eq!(bitsliced_select(ALL_ONES, &a, &b), a);
+        assert_eq!(bitsliced_select(ZERO, &a, &b), b);
+    }
+
+    #[test]
+    fn test_select_inline_works() {
+        let mut a = broadcast(1);
+        let b = broadcast(2);
+        bitsliced_select_inline(splat(0xFFFFFFFF00000000), &mut a, &b);
+        assert_eq!(a[62], splat(0x00000000FFFFFFFF));
+        assert_eq!(a[63], splat(0xFFFFFFFF00000000));
+    }
+
+    #[test]
+    fn test_min_max_works() {
+        //columns in the first half of every lane hold 2, the others 1
+        let mut a = [ZERO; 64];
+        a[62] = splat(0xFFFFFFFF00000000);
+        a[63] = splat(0x00000000FFFFFFFF);
+        let b = broadcast(u64::MAX);
+        assert_eq!(bitsliced_min(&a, &b), a);
+        assert_eq!(bitsliced_max(&a, &b), b);
+        let b = broadcast(1);
+        assert_eq!(bitsliced_min(&a, &b), b);
+        assert_eq!(bitsliced_max(&a, &b), a);
+    }
+
+    #[test]
+    fn test_shift_works() {
+        let x = 0x0123456789ABCDEFu64;
+        for k in [0, 1, 7, 32, 63] {
+            assert_eq!(bitsliced_shl(&broadcast(x), k), broadcast(x << k));
+            assert_eq!(bitsliced_shr(&broadcast(x), k), broadcast(x >> k));
+        }
+        assert_eq!(bitsliced_shl(&broadcast(x), 64), [ZERO; 64]);
+        assert_eq!(bitsliced_shr(&broadcast(x), 100), [ZERO; 64]);
+    }
+
+    #[test]
+    fn test_shift_inline_works() {
+        let x = 0xFEDCBA9876543210u64;
+        let mut a = broadcast(x);
+        bitsliced_shl_inline(&mut a, 12);
+        assert_eq!(a, broadcast(x << 12));
+        let mut a = broadcast(x);
+        bitsliced_shr_inline(&mut a, 12);
+        assert_eq!(a, broadcast(x >> 12));
+    }
+
+    #[test]
+    fn test_rotate_works() {
+        let x = 0x0123456789ABCDEFu64;
+        for k in [0, 1, 7, 32, 63, 64, 100] {
+            let expected_l = broadcast(x.rotate_left(k as u32));
+            let expected_r = broadcast(x.rotate_right(k as u32));
+            assert_eq!(bitsliced_rotl(&broadcast(x), k), expected_l);
+            assert_eq!(bitsliced_rotr(&broadcast(x), k), expected_r);
+            let mut a = broadcast(x);
+            bitsliced_rotl_inline(&mut a, k);
+            assert_eq!(a, expected_l);
+            let mut a = broadcast(x);
+            bitsliced_rotr_inline(&mut a, k);
+            assert_eq!(a, expected_r);
+        }
+    }
+
+    #[test]
+    fn test_shift_var_works() {
+        let x = 0x0123456789ABCDEFu64;
+        let a = broadcast(x);
+        //the lowest 32 columns of every lane get shifted by 5 (0b000101), the others by 58 (0b111010)
+        let mut amount = [ZERO; 6];
+        amount[0] = splat(0xFFFFFFFF00000000);
+        amount[1] = splat(0xFFFFFFFF00000000);
+        amount[2] = splat(0xFFFFFFFF00000000);
+        amount[3] = splat(0x00000000FFFFFFFF);
+        amount[4] = splat(0xFFFFFFFF00000000);
+        amount[5] = splat(0x00000000FFFFFFFF);
+        let lo = splat(0x00000000FFFFFFFF);
+        let hi = splat(0xFFFFFFFF00000000);
+        let expect = |l: u64, h: u64| {
+            let (l, h) = (broadcast(l), broadcast(h));
+            let mut out = [ZERO; 64];
+            for (i, row) in out.iter_mut().enumerate() {
+                *row = (l[i] & lo) | (h[i] & hi);
+            }
+            out
+        };
+        assert_eq!(bitsliced_shl_var(&a, &amount), expect(x << 5, x << 58));
+        assert_eq!(bitsliced_shr_var(&a, &amount), expect(x >> 5, x >> 58));
+        assert_eq!(
+            bitsliced_rotl_var(&a, &amount),
+            expect(x.rotate_left(5), x.rotate_left(58))
+        );
+        assert_eq!(
+            bitsliced_rotr_var(&a, &amount),
+            expect(x.rotate_right(5), x.rotate_right(58))
+        );
+    }
+
+    #[test]
+    fn test_shift_var_inline_works() {
+        let x = 0xFEDCBA9876543210u64;
+        //shift all columns by 65 (0b1000001), shifts clear everything, rotations rotate by 1
+        let mut amount = [ZERO; 7];
+        amount[0] = ALL_ONES;
+        amount[6] = ALL_ONES;
+        let mut a = broadcast(x);
+        bitsliced_shl_var_inline(&mut a, &amount);
+        assert_eq!(a, [ZERO; 64]);
+        let mut a = broadcast(x);
+        bitsliced_shr_var_inline(&mut a, &amount);
+        assert_eq!(a, [ZERO; 64]);
+        let mut a = broadcast(x);
+        bitsliced_rotl_var_inline(&mut a, &amount);
+        assert_eq!(a, broadcast(x.rotate_left(1)));
+        let mut a = broadcast(x);
+        bitsliced_rotr_var_inline(&mut a, &amount);
+        assert_eq!(a, broadcast(x.rotate_right(1)));
+
+        //with a single row, shifting by 1 clears it and rotating keeps it
+        let one = [u64::MAX; 1];
+        assert_eq!(bitsliced_shl_var(&one, &one), [0]);
+        assert_eq!(bitsliced_shr_var(&one, &one), [0]);
+        assert_eq!(bitsliced_rotl_var(&one, &one), one);
+        let empty: [u64; 0] = [];
+        assert_eq!(bitsliced_rotl(&empty, 3), empty);
+        assert_eq!(bitsliced_rotr_var(&empty, &one), empty);
+    }
+
+    //every column holds n, wider than 64 bits if N > 64
+    fn broadcast_n<const N: usize>(n: u128) -> [u64x8; N] {
+        let mut out = [ZERO; N];
+        for (i, row) in out.iter_mut().enumerate() {
+            let shift = N - 1 - i;
+            if shift < 128 && (n >> shift) & 1 == 1 {
+                *row = ALL_ONES;
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_32_rows_works() {
+        let x = 0xFEDCBA98u32;
+        let a = broadcast_n::<32>(x as u128);
+        let (sum, carry) = bitsliced_add_single_checked(&a, 0x12345678);
+        assert_eq!(sum, broadcast_n(x.wrapping_add(0x12345678) as u128));
+        assert_eq!(carry, ALL_ONES);
+        let b = broadcast_n::<32>(0x12345678);
+        assert_eq!(
+            bitsliced_sub(&b, &a),
+            broadcast_n(0x12345678u32.wrapping_sub(x) as u128)
+        );
+        assert_eq!(
+            bitsliced_mul_single(&a, 3),
+            broadcast_n(x.wrapping_mul(3) as u128)
+        );
+        assert_eq!(bitsliced_rotl(&a, 7), broadcast_n(x.rotate_left(7) as u128));
+        assert_eq!(bitsliced_shr(&a, 40), [ZERO; 32]);
+        assert_eq!(bitsliced_lt_single(&a, 1 << 40), ALL_ONES);
+        assert_eq!(bitsliced_eq_single(&a, (1 << 40) | x as u64), ZERO);
+        assert_eq!(bitsliced_eq_single(&a, x as u64), ALL_ONES);
+        //m doesn't fit in 32 bits, so nothing changes
+        assert_eq!(bitsliced_modulo(&a, 1 << 40).unwrap(), a);
+        assert_eq!(
+            bitsliced_modulo(&a, 1000).unwrap(),
+            broadcast_n((x % 1000) as u128)
+        );
+        assert!(bitsliced_modulo_power_of_two(&a, 33).is_err());
+        //b doesn't fit in 32 bits, so every column overflows/underflows
+        let (_, carry) = bitsliced_add_single_checked(&[ZERO; 32], 1 << 40);
+        assert_eq!(carry, ALL_ONES);
+        let mut c = [ZERO; 32];
+        assert_eq!(
+            bitsliced_add_single_checked_inline(&mut c, 1 << 40),
+            ALL_ONES
+        );
+        assert_eq!(
+            bitsliced_add_single_saturating(&[ZERO; 32], 1 << 40),
+            [ALL_ONES; 32]
+        );
+        let (_, borrow) = bitsliced_sub_single_with_borrow(&[ZERO; 32], 1 << 40);
+        assert_eq!(borrow, bitsliced_lt_single(&[ZERO; 32], 1 << 40));
+        assert_eq!(borrow, ALL_ONES);
+        let mut c = [ZERO; 32];
+        assert_eq!(
+            bitsliced_sub_single_with_borrow_inline(&mut c, 1 << 40),
+            ALL_ONES
+        );
+        let expected = ((x as u64 * 26u64.pow(5)) >> 32) as u128;
+        assert_eq!(bitsliced_fastrange(&a, 26u64.pow(5)), broadcast_n(expected));
+    }
+
+    #[test]
+    fn test_56_rows_works() {
+        let key = broadcast_n::<56>(0xFFFFFFFFFFFFFF);
+        let reduced = reduction_power_of_two(&key, 2, 56).unwrap();
+        assert_eq!(reduced, broadcast_n(1));
+        let reduced = reduction_mod(&key, 2, 95u64.pow(8)).unwrap();
+        assert_eq!(reduced, broadcast_n(1 % 95u128.pow(8)));
+    }
+
+    #[test]
+    fn test_128_rows_works() {
+        let x = 0xFEDCBA9876543210u64;
+        let y = 0x0123456789ABCDEFu64;
+        let a = broadcast_n::<128>(x as u128);
+        let b = broadcast_n::<128>(y as u128);
+        let (low, high) = bitsliced_mul(&a, &b);
+        assert_eq!(low, broadcast_n(x as u128 * y as u128));
+        assert_eq!(high, [ZERO; 128]);
+        let big = (1u128 << 100) + 5;
+        let a = broadcast_n::<128>(big);
+        assert_eq!(bitsliced_add(&a, &b), broadcast_n(big + y as u128));
+        assert_eq!(bitsliced_gt_single(&a, u64::MAX), ALL_ONES);
+        let m = 26u64.pow(7);
+        assert_eq!(
+            bitsliced_modulo(&a, m).unwrap(),
+            broadcast_n(big % m as u128)
+        );
+        assert_eq!(bitsliced_shl(&a, 20), broadcast_n(big << 20));
+        let mut amount = [ZERO; 7];
+        amount[0] = ALL_ONES;
+        amount[6] = ALL_ONES;
+        //rotate by 65
+        assert_eq!(
+            bitsliced_rotr_var(&a, &amount),
+            broadcast_n(big.rotate_right(65))
+        );
+        assert_eq!(bitsliced_shr_var(&a, &amount), broadcast_n(big >> 65));
+    }
+
+    fn check_lane<L: Lane>() {
+        let x = 0xFEDCBA9876543210u64;
+        let mut a = [L::ZERO; 64];
+        for (i, row) in a.iter_mut().enumerate() {
+            if (x >> (63 - i)) & 1 == 1 {
+                *row = L::ALL_ONES;
+            }
+        }
+        let (sum, carry) = bitsliced_add_single_checked(&a, x);
+        assert_eq!(sum, bitsliced_shl(&a, 1));
+        assert_eq!(carry, L::ALL_ONES);
+        assert_eq!(bitsliced_sub(&sum, &a), a);
+        assert_eq!(bitsliced_eq_single(&a, x), L::ALL_ONES);
+        assert_eq!(bitsliced_lt(&a, &sum), L::ZERO);
+        let m = 26u64.pow(7);
+        let reduced = reduction_mod(&a, 1, m).unwrap();
+        assert_eq!(bitsliced_eq_single(&reduced, (x + 1) % m), L::ALL_ONES);
+        let reduced = des_reduction(&a, 1);
+        assert_eq!(
+            bitsliced_eq_single(&reduced, (x + 1) % (1 << 56)),
+            L::ALL_ONES
+        );
+    }
+
+    #[test]
+    fn test_lanes_work() {
+        check_lane::<u64>();
+        check_lane::<wide::u64x2>();
+        check_lane::<wide::u64x4>();
+        check_lane::<u64x8>();
+    }
+
     #[test]
     fn test_modulo_works() {
         let a = [ALL_ONES; 64];
@@ -223,4 +1916,180 @@ mod tests {
             assert_eq!(a[i], ALL_ONES);
         }
     }
+
+    #[test]
+    fn test_transpose_backend_works() {
+        let input = random_matrices(1)[0];
+        let expected = crate::transpose::transpose_scalar(&input);
+
+        assert!(transpose_backend().is_supported());
+        assert_eq!(try_transpose_backend().unwrap(), transpose_backend());
+        assert_eq!(transpose_64x64(&input), expected);
+        let mut matrix = input;
+        transpose_64x64_inplace(&mut matrix);
+        assert_eq!(matrix, expected);
+        for backend in TransposeBackend::ALL {
+            let mut matrix = input;
+            assert_eq!(
+                transpose_64x64_inplace_with(backend, &mut matrix).is_ok(),
+                backend.is_supported()
+            );
+            match transpose_64x64_with(backend, &input) {
+                Ok(transposed) => {
+                    assert!(backend.is_supported());
+                    assert_eq!(transposed, expected);
+                    assert_eq!(matrix, expected);
+                }
+                Err(e) => {
+                    assert!(!backend.is_supported());
+                    assert_eq!(e.kind(), ErrorKind::Unsupported);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_transpose_backend_parse_works() {
+        for backend in TransposeBackend::ALL {
+            let name = format!("{:?}", backend);
+            assert_eq!(name.parse::<TransposeBackend>().unwrap(), backend);
+            assert_eq!(
+                name.to_uppercase().parse::<TransposeBackend>().unwrap(),
+                backend
+            );
+        }
+        assert!("sse".parse::<TransposeBackend>().is_err());
+    }
+
+    #[test]
+    fn test_to_bitsliced_works() {
+        assert_eq!(to_bitsliced(&[5; 512]), broadcast(5));
+
+        let input: [u64; 512] = random_matrices(8).as_flattened().try_into().unwrap();
+        let a = to_bitsliced(&input);
+        //integer 65 is column 65: lane 1, bit 62
+        for (i, row) in a.iter().enumerate() {
+            assert_eq!(row.to_array()[1] >> 62 & 1, input[65] >> (63 - i) & 1);
+        }
+        assert_eq!(from_bitsliced(&a), input);
+
+        let sum = from_bitsliced(&bitsliced_add_single(&a, 1));
+        for (n, s) in input.iter().zip(sum) {
+            assert_eq!(n.wrapping_add(1), s);
+        }
+    }
+
+    #[test]
+    fn test_transpose_shapes_work() {
+        let rows: [u64; 128] = random_matrices(2).as_flattened().try_into().unwrap();
+        let input8: [u8; 8] = std::array::from_fn(|i| rows[i] as u8);
+        let input32: [u32; 32] = std::array::from_fn(|i| rows[i] as u32);
+        let input128: [u128; 128] =
+            std::array::from_fn(|i| (rows[i] as u128) << 64 | rows[127 - i] as u128);
+        let expected8 = crate::transpose::transpose_8x8_scalar(&input8);
+        let expected32 = crate::transpose::transpose_32x32_scalar(&input32);
+        let expected128 = crate::transpose::transpose_128x128_scalar(&input128);
+
+        assert_eq!(transpose_8x8(&input8), expected8);
+        assert_eq!(transpose_32x32(&input32), expected32);
+        assert_eq!(transpose_128x128(&input128), expected128);
+        for backend in TransposeBackend::ALL {
+            if !backend.is_supported() {
+                assert!(transpose_8x8_with(backend, &input8).is_err());
+                assert!(transpose_32x32_with(backend, &input32).is_err());
+                assert!(transpose_128x128_with(backend, &input128).is_err());
+                continue;
+            }
+            assert_eq!(transpose_8x8_with(backend, &input8).unwrap(), expected8);
+            assert_eq!(transpose_32x32_with(backend, &input32).unwrap(), expected32);
+            assert_eq!(
+                transpose_128x128_with(backend, &input128).unwrap(),
+                expected128
+            );
+        }
+    }
+
+    #[test]
+    fn test_transpose_64xn_works() {
+        //64 DES keys of 56 bits
+        let keys = random_matrices(1)[0].map(|k| k >> 8);
+        let rows = transpose_64xn::<56>(&keys);
+        assert_eq!(rows[..], transpose_64x64(&keys)[8..]);
+        //bit 0 of key 3 is in the last row, column 3
+        assert_eq!(rows[55] >> (63 - 3) & 1, keys[3] & 1);
+        assert_eq!(transpose_nx64(&rows), keys);
+
+        for backend in TransposeBackend::ALL
+            .into_iter()
+            .filter(|b| b.is_supported())
+        {
+            assert_eq!(transpose_64xn_with::<56>(backend, &keys).unwrap(), rows);
+            assert_eq!(transpose_nx64_with(backend, &rows).unwrap(), keys);
+        }
+        assert_eq!(transpose_64xn::<64>(&keys), transpose_64x64(&keys));
+        assert_eq!(transpose_64xn::<0>(&keys), []);
+    }
+
+    #[test]
+    fn test_strings_to_bitsliced_works() {
+        let strings: Vec<Vec<u8>> = (0..300u32)
+            .map(|k| format!("pass{}", k * 7919).into_bytes())
+            .collect();
+        let refs: Vec<&[u8]> = strings.iter().map(|s| s.as_slice()).collect();
+
+        //12 bytes: one full 64 row transpose and one of 32 rows
+        let block = strings_to_bitsliced::<96>(&refs).unwrap();
+        //string 70 is column 70: lane 1, bit 57, the first row is the MSB of 'p'
+        let bit = |row: usize| block[row].to_array()[1] >> 57 & 1;
+        assert_eq!(bit(0), (b'p' >> 7) as u64);
+        assert_eq!(bit(1), (b'p' >> 6 & 1) as u64);
+        assert_eq!(bit(8 + 7), (b'a' & 1) as u64);
+
+        let lengths: Vec<usize> = strings.iter().map(|s| s.len()).collect();
+        assert_eq!(bitsliced_to_strings(&block, &lengths).unwrap(), strings);
+        //columns past the packed strings are zero padding
+        let recovered = bitsliced_to_strings(&block, &[12; 512]).unwrap();
+        assert!(recovered[300..].iter().all(|s| *s == [0; 12]));
+        assert!(bitsliced_to_strings(&block, &[0; 513]).is_err());
+        assert!(bitsliced_to_strings(&block, &[13]).is_err());
+
+        //a single byte: 8 rows
+        let block = strings_to_bitsliced::<8>(&[b"\x01".as_slice(), b"\x80"]).unwrap();
+        assert_eq!(block[7].to_array()[0] >> 63, 1);
+        assert_eq!(block[0].to_array()[0] >> 62 & 1, 1);
+        assert_eq!(
+            bitsliced_to_strings(&block, &[1, 1]).unwrap(),
+            [b"\x01", b"\x80"]
+        );
+
+        //strings that end in zero bytes come back whole
+        let nul: [&[u8]; 3] = [b"ab\0", b"\0", b""];
+        let block = strings_to_bitsliced::<32>(&nul).unwrap();
+        assert_eq!(bitsliced_to_strings(&block, &[3, 1, 0]).unwrap(), nul);
+
+        assert!(strings_to_bitsliced::<32>(&refs).is_err());
+        assert!(strings_to_bitsliced::<96>(&vec![b"a".as_slice(); 513]).is_err());
+    }
+
+    #[test]
+    fn test_transpose_many_works() {
+        let input = random_matrices(10_000);
+        let expected: Vec<[u64; 64]> = input.iter().map(transpose_64x64).collect();
+
+        let mut output = vec![[0u64; 64]; input.len()];
+        transpose_many(&input, &mut output);
+        assert_eq!(output, expected);
+
+        let mut output = vec![[0u64; 64]; input.len()];
+        transpose_many_parallel(&input, &mut output);
+        assert_eq!(output, expected);
+
+        transpose_many_parallel(&[], &mut []);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_transpose_many_length_mismatch_panics() {
+        transpose_many(&[[0; 64]; 2], &mut [[0; 64]; 3]);
+    }
 }
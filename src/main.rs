@@ -1,11 +1,17 @@
 use std::{env, hint::black_box};
 
+#[cfg(target_arch = "aarch64")]
+use bitsliced_op::transpose::transpose_neon;
+#[cfg(target_arch = "x86_64")]
+use bitsliced_op::transpose::{transpose_avx2, transpose_gfni, transpose_gfni_x8};
 use bitsliced_op::{
     benchmark::benchmark,
     bitsliced_add, bitsliced_add_inline, bitsliced_modulo_power_of_two,
     bitsliced_modulo_power_of_two_inline,
-    transpose::{transpose_gfni, transpose_scalar},
-    transpose_64x64,
+    bulk::{bitsliced_add_single_bulk, simd_width},
+    to_bitsliced,
+    transpose::transpose_scalar,
+    transpose_64x64, transpose_backend, transpose_many, transpose_many_parallel,
 };
 use wide::u64x8;
 
@@ -50,6 +56,21 @@ fn start_benchmark(benchmark_name: &str) {
                 bitsliced_add_inline(&mut a, &b);
             });
         }
+        "basb" | "bitsliced_addition_single_bulk" => {
+            let zero = u64x8::ZERO;
+            let mut a = vec![[zero; 64]; 4];
+
+            println!("using {:?}", simd_width());
+            benchmark(
+                "bitsliced_addition_single_bulk",
+                1_000_000,
+                10000,
+                64 * 4,
+                || {
+                    bitsliced_add_single_bulk(&mut a, 1);
+                },
+            );
+        }
         "bm" | "bitsliced_modulo" => {
             let all_ones = u64x8::splat(0xFFFFFFFFFFFFFFFF);
             let a = [all_ones; 64];
@@ -73,6 +94,7 @@ fn start_benchmark(benchmark_name: &str) {
                 let _ = transpose_scalar(&transpose_input);
             });
         }
+        #[cfg(target_arch = "x86_64")]
         "tg" | "transpose_gfni" => {
             let transpose_input = [0u64; 64];
 
@@ -80,9 +102,57 @@ fn start_benchmark(benchmark_name: &str) {
                 let _ = transpose_gfni(&transpose_input);
             });
         }
+        #[cfg(target_arch = "x86_64")]
+        "ta" | "transpose_avx2" => {
+            let transpose_input = [0u64; 64];
+
+            benchmark("transpose_avx2", 1_000_000, 10000, 1, || unsafe {
+                let _ = transpose_avx2(&transpose_input);
+            });
+        }
+        #[cfg(target_arch = "aarch64")]
+        "tn" | "transpose_neon" => {
+            let transpose_input = [0u64; 64];
+
+            benchmark("transpose_neon", 1_000_000, 10000, 1, || {
+                let _ = transpose_neon(&transpose_input);
+            });
+        }
+        #[cfg(target_arch = "x86_64")]
+        "tg8" | "transpose_gfni_x8" => {
+            let transpose_input = [0u64; 512];
+
+            benchmark("transpose_gfni_x8", 1_000_000, 10000, 8, || unsafe {
+                let _ = transpose_gfni_x8(&transpose_input);
+            });
+        }
+        "tb" | "to_bitsliced" => {
+            let transpose_input = [0u64; 512];
+
+            benchmark("to_bitsliced", 1_000_000, 10000, 8, || {
+                let _ = to_bitsliced(&transpose_input);
+            });
+        }
+        "tm" | "transpose_many" => {
+            let transpose_input = vec![[0u64; 64]; 1 << 16];
+            let mut transpose_output = vec![[0u64; 64]; 1 << 16];
+
+            benchmark("transpose_many", 100, 10, 1 << 16, || {
+                transpose_many(&transpose_input, &mut transpose_output);
+            });
+        }
+        "tmp" | "transpose_many_parallel" => {
+            let transpose_input = vec![[0u64; 64]; 1 << 16];
+            let mut transpose_output = vec![[0u64; 64]; 1 << 16];
+
+            benchmark("transpose_many_parallel", 100, 10, 1 << 16, || {
+                transpose_many_parallel(&transpose_input, &mut transpose_output);
+            });
+        }
         "tr" | "transpose" => {
             let transpose_input = [0u64; 64];
 
+            println!("using {:?}", transpose_backend());
             benchmark("transpose", 1_000_000, 10000, 1, || {
                 let _ = transpose_64x64(&transpose_input);
             });
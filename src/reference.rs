@@ -0,0 +1,617 @@
+//scalar reference for the bitsliced operations in lib.rs
+//every function works on a single column, masks are a bool per column
+//the tests below bitslice random columns, run the bitsliced op and compare every column with these
+
+use std::fmt::Debug;
+
+//integer that holds one column: u32 for 32 rows, u64 for 64 rows, u128 for 128 rows
+//add, sub, the comparisons and modulo work on all of them, the other operations on u64
+pub trait Column: Copy + Debug + Ord {
+    const MAX: Self;
+    //the lowest bits of x that fit in a column
+    fn truncate(x: u128) -> Self;
+    fn widen(self) -> u128;
+    fn overflowing_add(self, rhs: Self) -> (Self, bool);
+    fn overflowing_sub(self, rhs: Self) -> (Self, bool);
+}
+
+macro_rules! impl_column {
+    ($($t:ty),*) => {
+        $(
+            impl Column for $t {
+                const MAX: Self = <$t>::MAX;
+
+                fn truncate(x: u128) -> Self {
+                    x as $t
+                }
+
+                fn widen(self) -> u128 {
+                    self as u128
+                }
+
+                fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+                    <$t>::overflowing_add(self, rhs)
+                }
+
+                fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+                    <$t>::overflowing_sub(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_column!(u32, u64, u128);
+
+//true if b fits in a column, the *_single ops take b as a u64 so it can be wider than the column
+fn fits<T: Column>(b: u64) -> bool {
+    b as u128 <= T::MAX.widen()
+}
+
+pub fn add<T: Column>(a: T, b: T) -> T {
+    a.overflowing_add(b).0
+}
+
+//(sum, carry out)
+pub fn add_checked<T: Column>(a: T, b: T) -> (T, bool) {
+    a.overflowing_add(b)
+}
+
+pub fn add_saturating<T: Column>(a: T, b: T) -> T {
+    match a.overflowing_add(b) {
+        (_, true) => T::MAX,
+        (sum, false) => sum,
+    }
+}
+
+//the bits of b that don't fit are dropped from the sum, but still count as a carry
+pub fn add_single_checked<T: Column>(a: T, b: u64) -> (T, bool) {
+    let (sum, carry) = a.overflowing_add(T::truncate(b as u128));
+    (sum, carry || !fits::<T>(b))
+}
+
+pub fn add_single_saturating<T: Column>(a: T, b: u64) -> T {
+    match add_single_checked(a, b) {
+        (_, true) => T::MAX,
+        (sum, false) => sum,
+    }
+}
+
+pub fn sub<T: Column>(a: T, b: T) -> T {
+    a.overflowing_sub(b).0
+}
+
+//(difference, borrow out), borrow is set when a < b
+pub fn sub_with_borrow<T: Column>(a: T, b: T) -> (T, bool) {
+    a.overflowing_sub(b)
+}
+
+pub fn sub_single_with_borrow<T: Column>(a: T, b: u64) -> (T, bool) {
+    let (diff, borrow) = a.overflowing_sub(T::truncate(b as u128));
+    (diff, borrow || !fits::<T>(b))
+}
+
+//lower 64 bits of the product
+pub fn mul_single(a: u64, b: u64) -> u64 {
+    a.wrapping_mul(b)
+}
+
+//(low, high) of the 128-bit product
+pub fn mul(a: u64, b: u64) -> (u64, u64) {
+    let product = a as u128 * b as u128;
+    (product as u64, (product >> 64) as u64)
+}
+
+pub fn fastrange(a: u64, n: u64) -> u64 {
+    mul(a, n).1
+}
+
+pub fn eq<T: Column>(a: T, b: T) -> bool {
+    a == b
+}
+
+pub fn lt<T: Column>(a: T, b: T) -> bool {
+    a < b
+}
+
+pub fn le<T: Column>(a: T, b: T) -> bool {
+    a <= b
+}
+
+pub fn gt<T: Column>(a: T, b: T) -> bool {
+    a > b
+}
+
+pub fn ge<T: Column>(a: T, b: T) -> bool {
+    a >= b
+}
+
+//the *_single comparisons compare with b as a full u64, even if it doesn't fit in a column
+pub fn eq_single<T: Column>(a: T, b: u64) -> bool {
+    a.widen() == b as u128
+}
+
+pub fn lt_single<T: Column>(a: T, b: u64) -> bool {
+    a.widen() < b as u128
+}
+
+pub fn le_single<T: Column>(a: T, b: u64) -> bool {
+    a.widen() <= b as u128
+}
+
+pub fn gt_single<T: Column>(a: T, b: u64) -> bool {
+    a.widen() > b as u128
+}
+
+pub fn ge_single<T: Column>(a: T, b: u64) -> bool {
+    a.widen() >= b as u128
+}
+
+pub fn select<T: Column>(mask: bool, a: T, b: T) -> T {
+    if mask { a } else { b }
+}
+
+pub fn min<T: Column>(a: T, b: T) -> T {
+    a.min(b)
+}
+
+pub fn max<T: Column>(a: T, b: T) -> T {
+    a.max(b)
+}
+
+//shifting by 64 or more clears the column, same as bitsliced_shl
+pub fn shl(a: u64, k: usize) -> u64 {
+    a.checked_shl(k as u32).filter(|_| k < 64).unwrap_or(0)
+}
+
+pub fn shr(a: u64, k: usize) -> u64 {
+    a.checked_shr(k as u32).filter(|_| k < 64).unwrap_or(0)
+}
+
+pub fn rotl(a: u64, k: usize) -> u64 {
+    a.rotate_left((k % 64) as u32)
+}
+
+pub fn rotr(a: u64, k: usize) -> u64 {
+    a.rotate_right((k % 64) as u32)
+}
+
+pub fn modulo_power_of_two(a: u64, k: usize) -> u64 {
+    if k >= 64 { a } else { a & ((1 << k) - 1) }
+}
+
+pub fn modulo<T: Column>(a: T, m: u64) -> T {
+    T::truncate(a.widen() % m as u128)
+}
+
+pub fn reduction_power_of_two(h: u64, i: u64, k: usize) -> u64 {
+    modulo_power_of_two(add(h, i), k)
+}
+
+pub fn des_reduction(h: u64, i: u64) -> u64 {
+    reduction_power_of_two(h, i, 56)
+}
+
+pub fn reduction_mod(h: u64, i: u64, m: u64) -> u64 {
+    modulo(add(h, i), m)
+}
+
+pub fn fastrange_reduction(h: u64, i: u64, n: u64) -> u64 {
+    fastrange(add(h, i), n)
+}
+
+#[cfg(test)]
+mod tests {
+    use wide::u64x8;
+
+    use super::*;
+    use crate::{test_util::Rng, *};
+
+    const ITERATIONS: usize = 8;
+
+    fn columns(rng: &mut Rng) -> [u64; 512] {
+        std::array::from_fn(|_| rng.column())
+    }
+
+    //b shares some columns with a, so equality is hit as well
+    fn columns_like<T: Column>(rng: &mut Rng, a: &[T; 512]) -> [T; 512] {
+        a.map(|a_k| match rng.next() % 4 {
+            0 => a_k,
+            1 => add(a_k, T::truncate(1)),
+            _ => T::truncate(((rng.column() as u128) << 64) | rng.column() as u128),
+        })
+    }
+
+    //column k is lane k / 64, bit 63 - k % 64
+    fn mask_bits(mask: u64x8) -> [bool; 512] {
+        let lanes = mask.to_array();
+        std::array::from_fn(|k| (lanes[k / 64] >> (63 - k % 64)) & 1 == 1)
+    }
+
+    fn from_bits(bits: &[bool; 512]) -> u64x8 {
+        let mut lanes = [0u64; 8];
+        for (k, bit) in bits.iter().enumerate() {
+            lanes[k / 64] |= (*bit as u64) << (63 - k % 64);
+        }
+        u64x8::new(lanes)
+    }
+
+    fn map2<T: Column>(a: &[T; 512], b: &[T; 512], f: impl Fn(T, T) -> T) -> [T; 512] {
+        std::array::from_fn(|k| f(a[k], b[k]))
+    }
+
+    fn mask2<T: Column>(a: &[T; 512], b: &[T; 512], f: impl Fn(T, T) -> bool) -> [bool; 512] {
+        std::array::from_fn(|k| f(a[k], b[k]))
+    }
+
+    //columns with other widths than 64 bits, row i holds bit N - 1 - i of every column
+    fn to_bitsliced_n<T: Column, const N: usize>(columns: &[T; 512]) -> [u64x8; N] {
+        let mut out = [u64x8::ZERO; N];
+        //64 rows at a time, starting at the LSB
+        for (j, rows) in out.rchunks_mut(64).enumerate() {
+            let integers = columns.map(|c| (c.widen() >> (64 * j)) as u64);
+            rows.copy_from_slice(&to_bitsliced(&integers)[64 - rows.len()..]);
+        }
+        out
+    }
+
+    fn from_bitsliced_n<T: Column, const N: usize>(input: &[u64x8; N]) -> [T; 512] {
+        let mut columns = [0u128; 512];
+        for (j, rows) in input.rchunks(64).enumerate() {
+            let mut matrix = [u64x8::ZERO; 64];
+            matrix[64 - rows.len()..].copy_from_slice(rows);
+            for (column, integer) in columns.iter_mut().zip(from_bitsliced(&matrix)) {
+                *column |= (integer as u128) << (64 * j);
+            }
+        }
+        columns.map(T::truncate)
+    }
+
+    fn random_columns<T: Column>(rng: &mut Rng) -> [T; 512] {
+        std::array::from_fn(|_| T::truncate(((rng.column() as u128) << 64) | rng.column() as u128))
+    }
+
+    //add, sub, the comparisons and modulo with T = u32/u128 for N = 32/128 rows
+    fn check_width<T: Column, const N: usize>(rng: &mut Rng) {
+        assert_eq!(T::MAX.widen().count_ones() as usize, N);
+        for _ in 0..ITERATIONS {
+            let a: [T; 512] = random_columns(rng);
+            let b = columns_like(rng, &a);
+            //also use values that don't fit in 32 bits for N = 32
+            let s = rng.column();
+            let (a_b, b_b) = (to_bitsliced_n::<T, N>(&a), to_bitsliced_n::<T, N>(&b));
+            let unslice = from_bitsliced_n::<T, N>;
+
+            assert_eq!(unslice(&bitsliced_add(&a_b, &b_b)), map2(&a, &b, add));
+            let (sum, carry) = bitsliced_add_checked(&a_b, &b_b);
+            assert_eq!(unslice(&sum), map2(&a, &b, add));
+            assert_eq!(mask_bits(carry), mask2(&a, &b, |a, b| add_checked(a, b).1));
+            assert_eq!(
+                unslice(&bitsliced_add_saturating(&a_b, &b_b)),
+                map2(&a, &b, add_saturating)
+            );
+            let (sum, carry) = bitsliced_add_single_checked(&a_b, s);
+            assert_eq!(unslice(&sum), a.map(|a_k| add_single_checked(a_k, s).0));
+            assert_eq!(mask_bits(carry), a.map(|a_k| add_single_checked(a_k, s).1));
+            assert_eq!(
+                unslice(&bitsliced_add_single_saturating(&a_b, s)),
+                a.map(|a_k| add_single_saturating(a_k, s))
+            );
+
+            let (diff, borrow) = bitsliced_sub_with_borrow(&a_b, &b_b);
+            assert_eq!(unslice(&diff), map2(&a, &b, sub));
+            assert_eq!(
+                mask_bits(borrow),
+                mask2(&a, &b, |a, b| sub_with_borrow(a, b).1)
+            );
+            let (diff, borrow) = bitsliced_sub_single_with_borrow(&a_b, s);
+            assert_eq!(
+                unslice(&diff),
+                a.map(|a_k| sub_single_with_borrow(a_k, s).0)
+            );
+            assert_eq!(
+                mask_bits(borrow),
+                a.map(|a_k| sub_single_with_borrow(a_k, s).1)
+            );
+
+            assert_eq!(mask_bits(bitsliced_eq(&a_b, &b_b)), mask2(&a, &b, eq));
+            assert_eq!(mask_bits(bitsliced_lt(&a_b, &b_b)), mask2(&a, &b, lt));
+            assert_eq!(mask_bits(bitsliced_ge(&a_b, &b_b)), mask2(&a, &b, ge));
+            for s in [s, a[0].widen() as u64] {
+                assert_eq!(
+                    mask_bits(bitsliced_eq_single(&a_b, s)),
+                    a.map(|a_k| eq_single(a_k, s))
+                );
+                assert_eq!(
+                    mask_bits(bitsliced_lt_single(&a_b, s)),
+                    a.map(|a_k| lt_single(a_k, s))
+                );
+                assert_eq!(
+                    mask_bits(bitsliced_gt_single(&a_b, s)),
+                    a.map(|a_k| gt_single(a_k, s))
+                );
+            }
+
+            for m in [3, 26u64.pow(5), 95u64.pow(8), u64::MAX, s.max(1)] {
+                assert_eq!(
+                    unslice(&bitsliced_modulo(&a_b, m).unwrap()),
+                    a.map(|a_k| modulo(a_k, m))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_other_widths_work() {
+        let mut rng = Rng(0x6C8E9CF570932BD5);
+        check_width::<u32, 32>(&mut rng);
+        check_width::<u128, 128>(&mut rng);
+    }
+
+    #[test]
+    fn test_add_works() {
+        let mut rng = Rng(0x9E3779B97F4A7C15);
+        for _ in 0..ITERATIONS {
+            let a = columns(&mut rng);
+            let b = columns_like(&mut rng, &a);
+            let s = rng.column();
+            let (a_b, b_b) = (to_bitsliced(&a), to_bitsliced(&b));
+
+            assert_eq!(
+                from_bitsliced(&bitsliced_add(&a_b, &b_b)),
+                map2(&a, &b, add)
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_add_single(&a_b, s)),
+                a.map(|a_k| add(a_k, s))
+            );
+
+            let (sum, carry) = bitsliced_add_checked(&a_b, &b_b);
+            assert_eq!(from_bitsliced(&sum), map2(&a, &b, add));
+            assert_eq!(mask_bits(carry), mask2(&a, &b, |a, b| add_checked(a, b).1));
+            let (sum, carry) = bitsliced_add_single_checked(&a_b, s);
+            assert_eq!(from_bitsliced(&sum), a.map(|a_k| add(a_k, s)));
+            assert_eq!(mask_bits(carry), a.map(|a_k| add_single_checked(a_k, s).1));
+
+            assert_eq!(
+                from_bitsliced(&bitsliced_add_saturating(&a_b, &b_b)),
+                map2(&a, &b, add_saturating)
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_add_single_saturating(&a_b, s)),
+                a.map(|a_k| add_single_saturating(a_k, s))
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_add_wrapping(&a_b, &b_b)),
+                map2(&a, &b, add)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sub_works() {
+        let mut rng = Rng(0xD1B54A32D192ED03);
+        for _ in 0..ITERATIONS {
+            let a = columns(&mut rng);
+            let b = columns_like(&mut rng, &a);
+            let s = rng.column();
+            let (a_b, b_b) = (to_bitsliced(&a), to_bitsliced(&b));
+
+            assert_eq!(
+                from_bitsliced(&bitsliced_sub(&a_b, &b_b)),
+                map2(&a, &b, sub)
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_sub_single(&a_b, s)),
+                a.map(|a_k| sub(a_k, s))
+            );
+
+            let (diff, borrow) = bitsliced_sub_with_borrow(&a_b, &b_b);
+            assert_eq!(from_bitsliced(&diff), map2(&a, &b, sub));
+            assert_eq!(
+                mask_bits(borrow),
+                mask2(&a, &b, |a, b| sub_with_borrow(a, b).1)
+            );
+            let (diff, borrow) = bitsliced_sub_single_with_borrow(&a_b, s);
+            assert_eq!(from_bitsliced(&diff), a.map(|a_k| sub(a_k, s)));
+            assert_eq!(
+                mask_bits(borrow),
+                a.map(|a_k| sub_single_with_borrow(a_k, s).1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_mul_works() {
+        let mut rng = Rng(0x2545F4914F6CDD1D);
+        for _ in 0..ITERATIONS {
+            let a = columns(&mut rng);
+            let b = columns(&mut rng);
+            let s = rng.column();
+            let (a_b, b_b) = (to_bitsliced(&a), to_bitsliced(&b));
+
+            assert_eq!(
+                from_bitsliced(&bitsliced_mul_single(&a_b, s)),
+                a.map(|a_k| mul_single(a_k, s))
+            );
+
+            let (low, high) = bitsliced_mul(&a_b, &b_b);
+            assert_eq!(from_bitsliced(&low), map2(&a, &b, |a, b| mul(a, b).0));
+            assert_eq!(from_bitsliced(&high), map2(&a, &b, |a, b| mul(a, b).1));
+
+            assert_eq!(
+                from_bitsliced(&bitsliced_fastrange(&a_b, s)),
+                a.map(|a_k| fastrange(a_k, s))
+            );
+        }
+    }
+
+    #[test]
+    fn test_compare_works() {
+        let mut rng = Rng(0xBF58476D1CE4E5B9);
+        for _ in 0..ITERATIONS {
+            let a = columns(&mut rng);
+            let b = columns_like(&mut rng, &a);
+            //a column of a, so some columns are equal to it
+            let s = a[rng.next() as usize % 512];
+            let (a_b, b_b) = (to_bitsliced(&a), to_bitsliced(&b));
+
+            assert_eq!(mask_bits(bitsliced_eq(&a_b, &b_b)), mask2(&a, &b, eq));
+            assert_eq!(mask_bits(bitsliced_lt(&a_b, &b_b)), mask2(&a, &b, lt));
+            assert_eq!(mask_bits(bitsliced_le(&a_b, &b_b)), mask2(&a, &b, le));
+            assert_eq!(mask_bits(bitsliced_gt(&a_b, &b_b)), mask2(&a, &b, gt));
+            assert_eq!(mask_bits(bitsliced_ge(&a_b, &b_b)), mask2(&a, &b, ge));
+
+            assert_eq!(
+                mask_bits(bitsliced_eq_single(&a_b, s)),
+                a.map(|a_k| eq_single(a_k, s))
+            );
+            assert_eq!(
+                mask_bits(bitsliced_lt_single(&a_b, s)),
+                a.map(|a_k| lt_single(a_k, s))
+            );
+            assert_eq!(
+                mask_bits(bitsliced_le_single(&a_b, s)),
+                a.map(|a_k| le_single(a_k, s))
+            );
+            assert_eq!(
+                mask_bits(bitsliced_gt_single(&a_b, s)),
+                a.map(|a_k| gt_single(a_k, s))
+            );
+            assert_eq!(
+                mask_bits(bitsliced_ge_single(&a_b, s)),
+                a.map(|a_k| ge_single(a_k, s))
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_works() {
+        let mut rng = Rng(0x94D049BB133111EB);
+        for _ in 0..ITERATIONS {
+            let a = columns(&mut rng);
+            let b = columns_like(&mut rng, &a);
+            let mask: [bool; 512] = std::array::from_fn(|_| rng.next() & 1 == 1);
+            let (a_b, b_b) = (to_bitsliced(&a), to_bitsliced(&b));
+
+            let expected: [u64; 512] = std::array::from_fn(|k| select(mask[k], a[k], b[k]));
+            assert_eq!(
+                from_bitsliced(&bitsliced_select(from_bits(&mask), &a_b, &b_b)),
+                expected
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_min(&a_b, &b_b)),
+                map2(&a, &b, min)
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_max(&a_b, &b_b)),
+                map2(&a, &b, max)
+            );
+        }
+    }
+
+    #[test]
+    fn test_shift_works() {
+        let mut rng = Rng(0x85EBCA6B27D4EB2F);
+        let a = columns(&mut rng);
+        let a_b = to_bitsliced(&a);
+        for k in [0, 1, 7, 31, 32, 56, 63, 64, 65, 127, 128] {
+            assert_eq!(
+                from_bitsliced(&bitsliced_shl(&a_b, k)),
+                a.map(|a_k| shl(a_k, k))
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_shr(&a_b, k)),
+                a.map(|a_k| shr(a_k, k))
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_rotl(&a_b, k)),
+                a.map(|a_k| rotl(a_k, k))
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_rotr(&a_b, k)),
+                a.map(|a_k| rotr(a_k, k))
+            );
+        }
+    }
+
+    #[test]
+    fn test_shift_var_works() {
+        let mut rng = Rng(0xC2B2AE3D27D4EB4F);
+        for _ in 0..ITERATIONS {
+            let a = columns(&mut rng);
+            //amounts in 0..128 as a bitsliced value with 7 rows, so shifts past 64 are covered too
+            let amount = columns(&mut rng).map(|x| x % 128);
+            let a_b = to_bitsliced(&a);
+            let amount_b: [u64x8; 7] = to_bitsliced(&amount)[57..].try_into().unwrap();
+
+            let expected = |f: fn(u64, usize) -> u64| map2(&a, &amount, |a, k| f(a, k as usize));
+            assert_eq!(
+                from_bitsliced(&bitsliced_shl_var(&a_b, &amount_b)),
+                expected(shl)
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_shr_var(&a_b, &amount_b)),
+                expected(shr)
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_rotl_var(&a_b, &amount_b)),
+                expected(rotl)
+            );
+            assert_eq!(
+                from_bitsliced(&bitsliced_rotr_var(&a_b, &amount_b)),
+                expected(rotr)
+            );
+        }
+    }
+
+    #[test]
+    fn test_modulo_works() {
+        let mut rng = Rng(0x165667B19E3779F9);
+        for _ in 0..ITERATIONS {
+            let a = columns(&mut rng);
+            let a_b = to_bitsliced(&a);
+            for k in [0, 1, 13, 56, 63, 64] {
+                assert_eq!(
+                    from_bitsliced(&bitsliced_modulo_power_of_two(&a_b, k).unwrap()),
+                    a.map(|a_k| modulo_power_of_two(a_k, k))
+                );
+            }
+            let m = rng.column().max(1);
+            for m in [1, 3, 26u64.pow(7), 95u64.pow(8), 1 << 40, u64::MAX, m] {
+                assert_eq!(
+                    from_bitsliced(&bitsliced_modulo(&a_b, m).unwrap()),
+                    a.map(|a_k| modulo(a_k, m))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reduction_works() {
+        let mut rng = Rng(0x27D4EB2F165667C5);
+        for _ in 0..ITERATIONS {
+            let h = columns(&mut rng);
+            let h_b = to_bitsliced(&h);
+            let i = rng.next() % 10000;
+            let m = rng.next() % (1 << 48) + 1;
+
+            assert_eq!(
+                from_bitsliced(&crate::des_reduction(&h_b, i)),
+                h.map(|h_k| reference::des_reduction(h_k, i))
+            );
+            assert_eq!(
+                from_bitsliced(&crate::reduction_power_of_two(&h_b, i, 40).unwrap()),
+                h.map(|h_k| reference::reduction_power_of_two(h_k, i, 40))
+            );
+            assert_eq!(
+                from_bitsliced(&crate::reduction_mod(&h_b, i, m).unwrap()),
+                h.map(|h_k| reference::reduction_mod(h_k, i, m))
+            );
+            assert_eq!(
+                from_bitsliced(&crate::fastrange_reduction(&h_b, i, m)),
+                h.map(|h_k| reference::fastrange_reduction(h_k, i, m))
+            );
+        }
+    }
+}
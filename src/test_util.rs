@@ -0,0 +1,46 @@
+use wide::u64x8;
+
+//seeded xorshift for the tests, so failures can be reproduced
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    //uniform values alone rarely hit long carry chains or the top bits,
+    //so some values are edge cases and runs of ones instead
+    pub fn column(&mut self) -> u64 {
+        let x = self.next();
+        match x % 8 {
+            0 => [0, 1, u64::MAX, u64::MAX - 1, 1 << 63, (1 << 63) - 1][(x >> 8) as usize % 6],
+            1 => u64::MAX >> ((x >> 8) % 64),
+            2 => u64::MAX << ((x >> 8) % 64),
+            _ => self.next(),
+        }
+    }
+}
+
+//matrices with different bits in every row
+pub fn random_matrices(count: usize) -> Vec<[u64; 64]> {
+    let mut rng = Rng(0x9E3779B97F4A7C15);
+    let mut matrices = vec![[0u64; 64]; count];
+    for row in matrices.iter_mut().flatten() {
+        *row = rng.next();
+    }
+    matrices
+}
+
+//blocks with different values in every column, the same bits as random_matrices(8 * count)
+pub fn random_blocks(count: usize) -> Vec<[u64x8; 64]> {
+    let rows = random_matrices(8 * count);
+    let mut lanes = rows.as_flattened().chunks_exact(8);
+    let mut blocks = vec![[u64x8::ZERO; 64]; count];
+    for row in blocks.iter_mut().flatten() {
+        *row = u64x8::new(lanes.next().unwrap().try_into().unwrap());
+    }
+    blocks
+}
@@ -1,7 +1,17 @@
+#[cfg(target_arch = "aarch64")]
+use core::arch::aarch64::*;
+#[cfg(target_arch = "x86_64")]
 use core::arch::x86_64::*;
+#[cfg(target_arch = "x86_64")]
+use wide::u64x8;
 
 pub fn transpose_scalar(input: &[u64; 64]) -> [u64; 64] {
     let mut out = *input;
+    transpose_scalar_inplace(&mut out);
+    out
+}
+
+pub fn transpose_scalar_inplace(out: &mut [u64; 64]) {
     let mut j = 32;
     let mut mask: u64 = 0x00000000FFFFFFFF;
 
@@ -19,85 +29,188 @@ pub fn transpose_scalar(input: &[u64; 64]) -> [u64; 64] {
         j >>= 1;
         mask ^= mask << j;
     }
-    out
 }
 
+//same butterfly as transpose_scalar for the other square sizes
+macro_rules! transpose_square_scalar {
+    ($name:ident, $t:ty, $n:expr) => {
+        pub fn $name(input: &[$t; $n]) -> [$t; $n] {
+            let mut out = *input;
+            let mut j = $n / 2;
+            let mut mask: $t = <$t>::MAX >> j;
+
+            while j > 0 {
+                for k in 0..$n {
+                    if (k & j) == 0 {
+                        let x = out[k];
+                        let y = out[k | j];
+
+                        let t = (x ^ (y >> j)) & mask;
+                        out[k] = x ^ t;
+                        out[k | j] = y ^ (t << j);
+                    }
+                }
+                j >>= 1;
+                mask ^= mask << j;
+            }
+            out
+        }
+    };
+}
+
+transpose_square_scalar!(transpose_8x8_scalar, u8, 8);
+transpose_square_scalar!(transpose_32x32_scalar, u32, 32);
+transpose_square_scalar!(transpose_128x128_scalar, u128, 128);
+
+//a single 8x8 block is one gf2p8affine instruction
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "gfni")]
+/// # Safety
+/// The cpu must support gfni (see `transpose_8x8` for a checked version).
+pub unsafe fn transpose_8x8_gfni(input: &[u8; 8]) -> [u8; 8] {
+    let x = _mm_set_epi64x(0, u64::from_le_bytes(*input) as i64);
+    let matrix = _mm_set1_epi64x(0x8040201008040201u64 as i64);
+    //byte b of the result is row 7 - b
+    let transposed = _mm_gf2p8affine_epi64_epi8(matrix, x, 0);
+    (_mm_cvtsi128_si64(transposed) as u64).to_be_bytes()
+}
+
+#[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx512f,gfni,avx512vbmi,avx512bw")]
+/// # Safety
+/// The cpu must support avx512f, avx512bw, avx512vbmi and gfni (see `transpose_64x64` for a checked version).
 pub unsafe fn transpose_gfni(input: &[u64; 64]) -> [u64; 64] {
-    let mut output = gather_bytes(input);
-    //TODO: do i really need to go out of SIMD after gathering bytes?
-    //i can keep data in 512 bit registers and avoid _mm512_set_epi64
-
-    //next steps SIMD GFNI and interleave
-    for index in 0..4 {
-        let i = index * 16;
-
-        let va = _mm512_set_epi64(
-            output[i + 15] as i64,
-            output[i + 13] as i64,
-            output[i + 11] as i64,
-            output[i + 9] as i64,
-            output[i + 7] as i64,
-            output[i + 5] as i64,
-            output[i + 3] as i64,
-            output[i + 1] as i64,
-        );
-        let vb = _mm512_set_epi64(
-            output[i + 14] as i64,
-            output[i + 12] as i64,
-            output[i + 10] as i64,
-            output[i + 8] as i64,
-            output[i + 6] as i64,
-            output[i + 4] as i64,
-            output[i + 2] as i64,
-            output[i + 0] as i64,
-        );
-        let gfni_va = gfni_bit_transpose_8x8(va);
-        let gfni_vb = gfni_bit_transpose_8x8(vb);
-        let interleaved = interleave_full(gfni_va, gfni_vb);
-        output[i..i + 8].copy_from_slice(&interleaved.0);
-        output[i + 8..i + 16].copy_from_slice(&interleaved.1);
-    }
-
+    let mut output = [0u64; 64];
+    gfni_store(gfni_transpose_registers(input), &mut output);
     output
 }
 
-unsafe fn gather_bytes(input: &[u64; 64]) -> [u64; 64] {
-    let mut output = [[0u64; 8]; 8];
-    let perm = make_perm();
-    let idx = _mm512_loadu_si512(perm.as_ptr() as *const _);
-    let ptr = input.as_ptr();
-    for i in 0..8 {
-        let v = _mm512_loadu_si512(ptr.add(i * 8) as *const _);
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,gfni,avx512vbmi,avx512bw")]
+/// # Safety
+/// The cpu must support avx512f, avx512bw, avx512vbmi and gfni (see `transpose_64x64_inplace` for a checked version).
+pub unsafe fn transpose_gfni_inplace(matrix: &mut [u64; 64]) {
+    //here and in the avx2/neon versions the whole matrix is in registers before the first store,
+    //so the result can be written back into the same buffer
+    gfni_store(gfni_transpose_registers(matrix), matrix);
+}
 
-        let transposed = _mm512_permutexvar_epi8(idx, v);
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+fn gfni_store(v: [__m512i; 8], output: &mut [u64; 64]) {
+    let ptr = output.as_mut_ptr();
+    for (i, rows) in v.iter().enumerate() {
+        unsafe { _mm512_storeu_si512(ptr.add(i * 8) as *mut _, *rows) };
+    }
+}
 
-        _mm512_storeu_si512(output[i].as_mut_ptr() as *mut _, transposed);
+//transpose 8 matrices at once, matrix j is input[64 * j..64 * j + 64] and ends up in lane j of every row
+//this is the layout to_bitsliced produces (and every bitsliced function consumes)
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,gfni,avx512vbmi,avx512bw")]
+/// # Safety
+/// The cpu must support avx512f, avx512bw, avx512vbmi and gfni (see `to_bitsliced` for a checked version).
+pub unsafe fn transpose_gfni_x8(input: &[u64; 512]) -> [u64x8; 64] {
+    //matrices[j][i] holds rows 8i..8i + 7 of transposed matrix j
+    let mut matrices = [[_mm512_setzero_si512(); 8]; 8];
+    for (matrix, rows) in matrices.iter_mut().zip(input.chunks_exact(64)) {
+        *matrix = gfni_transpose_registers(rows.try_into().unwrap());
     }
-    let mut aligned_output = [0u64; 64];
+
+    let mut output = [u64x8::ZERO; 64];
+    //u64x8 is 64 bytes that hold lane 0..7 in order, like __m512i
+    let ptr = output.as_mut_ptr() as *mut __m512i;
     for i in 0..8 {
-        for j in 0..8 {
-            aligned_output[i * 8 + j] = output[j][i];
+        //register j holds rows 8i..8i + 7 of matrix j, swap so register q holds row 8i + q of every matrix
+        let mut v = [_mm512_setzero_si512(); 8];
+        for (register, matrix) in v.iter_mut().zip(&matrices) {
+            *register = matrix[i];
+        }
+        transpose_8x8_qwords(&mut v);
+        for (q, register) in v.into_iter().enumerate() {
+            unsafe { _mm512_storeu_si512(ptr.add(i * 8 + q), register) };
         }
     }
-    aligned_output
+    output
 }
 
-fn make_perm() -> [u8; 64] {
+//register i holds rows 8i..8i + 7, the data stays in zmm registers from load to store
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,gfni,avx512vbmi,avx512bw")]
+fn gfni_transpose_registers(input: &[u64; 64]) -> [__m512i; 8] {
+    let scatter = unsafe { _mm512_loadu_si512(SCATTER_PERM.as_ptr() as *const _) };
+    let mut v = [_mm512_setzero_si512(); 8];
+    let ptr = input.as_ptr();
+    for (i, block) in v.iter_mut().enumerate() {
+        let rows = unsafe { _mm512_loadu_si512(ptr.add(i * 8) as *const _) };
+        *block = gfni_bit_transpose_8x8(gfni_gather_bytes(rows));
+    }
+
+    //register i needs the blocks that hold byte 7 - i (qword i of every register)
+    transpose_8x8_qwords(&mut v);
+
+    for block in v.iter_mut() {
+        *block = _mm512_permutexvar_epi8(scatter, *block);
+    }
+    v
+}
+
+//rows holds 8 rows, afterwards qword m holds byte 7 - m of all 8 rows (a 8x8 bit block)
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512vbmi")]
+fn gfni_gather_bytes(rows: __m512i) -> __m512i {
+    let gather = unsafe { _mm512_loadu_si512(GATHER_PERM.as_ptr() as *const _) };
+    _mm512_permutexvar_epi8(gather, rows)
+}
+
+#[cfg(any(target_arch = "x86_64", test))]
+const GATHER_PERM: [u8; 64] = make_perm();
+
+#[cfg(any(target_arch = "x86_64", test))]
+const SCATTER_PERM: [u8; 64] = make_scatter_perm();
+
+#[cfg(any(target_arch = "x86_64", test))]
+const fn make_perm() -> [u8; 64] {
     let mut p = [0u8; 64];
 
-    for byte_index in 0..8 {
-        for lane in 0..8 {
+    let mut byte_index = 0;
+    while byte_index < 8 {
+        let mut lane = 0;
+        while lane < 8 {
             let dst = byte_index * 8 + lane;
             let src = lane * 8 + (7 - byte_index);
             p[dst] = src as u8;
+            lane += 1;
         }
+        byte_index += 1;
     }
 
     p
 }
 
-unsafe fn gfni_bit_transpose_8x8(x: __m512i) -> __m512i {
+//after the gfni step byte b of qword j is byte 7 - j of row 7 - b
+#[cfg(any(target_arch = "x86_64", test))]
+const fn make_scatter_perm() -> [u8; 64] {
+    let mut p = [0u8; 64];
+
+    let mut qword = 0;
+    while qword < 8 {
+        let mut byte = 0;
+        while byte < 8 {
+            let dst = (7 - byte) * 8 + (7 - qword);
+            let src = qword * 8 + byte;
+            p[dst] = src as u8;
+            byte += 1;
+        }
+        qword += 1;
+    }
+
+    p
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,gfni")]
+fn gfni_bit_transpose_8x8(x: __m512i) -> __m512i {
     // Each byte: bit i selects input bit i
     let matrix = _mm512_set1_epi64(0x8040201008040201u64 as i64);
 
@@ -107,113 +220,199 @@ unsafe fn gfni_bit_transpose_8x8(x: __m512i) -> __m512i {
     _mm512_gf2p8affine_epi64_epi8(matrix, x, 0)
 }
 
-//interleave bytes 0 and 1 from all rows (first step e.g interleave bytes)
+//qword j of register i ends up in qword i of register j
+//every step swaps one bit of the register index with the same bit of the qword index
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+fn transpose_8x8_qwords(v: &mut [__m512i; 8]) {
+    for pair in v.chunks_exact_mut(2) {
+        let (a, b) = (pair[0], pair[1]);
+        pair[0] = _mm512_unpacklo_epi64(a, b);
+        pair[1] = _mm512_unpackhi_epi64(a, b);
+    }
+    let lo = _mm512_set_epi64(13, 12, 5, 4, 9, 8, 1, 0);
+    let hi = _mm512_set_epi64(15, 14, 7, 6, 11, 10, 3, 2);
+    for k in [0, 1, 4, 5] {
+        let (a, b) = (v[k], v[k | 2]);
+        v[k] = _mm512_permutex2var_epi64(a, lo, b);
+        v[k | 2] = _mm512_permutex2var_epi64(a, hi, b);
+    }
+    let lo = _mm512_set_epi64(11, 10, 9, 8, 3, 2, 1, 0);
+    let hi = _mm512_set_epi64(15, 14, 13, 12, 7, 6, 5, 4);
+    for k in 0..4 {
+        let (a, b) = (v[k], v[k | 4]);
+        v[k] = _mm512_permutex2var_epi64(a, lo, b);
+        v[k | 4] = _mm512_permutex2var_epi64(a, hi, b);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+/// # Safety
+/// The cpu must support avx2 (see `transpose_64x64` for a checked version).
+pub unsafe fn transpose_avx2(input: &[u64; 64]) -> [u64; 64] {
+    let mut output = [0u64; 64];
+    avx2_store(avx2_transpose_registers(input), &mut output);
+    output
+}
 
-unsafe fn interleave_full(va: __m512i, vb: __m512i) -> ([u64; 8], [u64; 8]) {
-    /*
-    let va = _mm512_set_epi64(
-        h1 as i64, f1 as i64, d1 as i64, b1 as i64, h0 as i64, f0 as i64, d0 as i64, b0 as i64,
-    );
-    let vb = _mm512_set_epi64(
-        g1 as i64, e1 as i64, c1 as i64, a1 as i64, g0 as i64, e0 as i64, c0 as i64, a0 as i64,
-    );*/
-    //(row1 comes here)...(row0) E00F00E01F01E02F02E03F03(hi) E04F04E05F05E06F06E07F07(lo) A00B00A01B01A02B02A03B03(hi) A04B04A05B05A06B06A07B07(lo)
-    let lo = _mm512_unpacklo_epi8(va, vb);
-    //...gh0hi gh0lo cd0hi cd0lo
-    let hi = _mm512_unpackhi_epi8(va, vb);
-
-    //...E04F04G04H04E05F05G05H05 E06F06G06H06E07F07G07H07 A04B04C04D04A05B05C05D05(hi) A06B06C06D06A07B07C07D07(lo)
-    let lo_1 = _mm512_unpacklo_epi16(hi, lo);
-    //...E00F00G00H00E01F01G01H01 E02F02G02H02E03F03G03H03 A00B00C00D00A01B01C01D01(hi) A02B02C02D02A03B03C03D03(lo)
-    let hi_1 = _mm512_unpackhi_epi16(hi, lo);
-
-    //shuffly lanes in right order
-    //...(row1 before) (row0) A00B00C00D00A01B01C01D01(hi) A02B02C02D02A03B03C03D03(lo) A04B04C04D04A05B05C05D05(hi) A06B06C06D06A07B07C07D07(lo)
-    let a = grab_128_lanes_a(lo_1, hi_1);
-    //...(row1 before) (row0) E00F00G00H00E01F01G01H01 E02F02G02H02E03F03G03H03 E04F04G04H04E05F05G05H05 E06F06G06H06E07F07G07H07
-    let b = grab_128_lanes_b(lo_1, hi_1);
-
-    //...A02B02C02D02E02F02G02H02 A03B03C03D03E03F03G03H03 A06B06C06D06E06F06G06H06 A07B07C07D07E07F07G07H07
-    let lo_2 = _mm512_unpacklo_epi32(b, a);
-    //...A00B00C00D00E00F00G00H00 A01B01C01D01E01F01G01H01 A04B04C04D04E04F04G04H04 A05B05C05D05E05F05G05H05
-    let hi_2 = _mm512_unpackhi_epi32(b, a);
-
-    // row 0
-    let l = _mm512_extracti64x2_epi64(hi_2, 1);
-    let final_0 = _mm_extract_epi64(l, 1) as u64;
-    let final_1 = _mm_cvtsi128_si64(l) as u64;
-
-    let l = _mm512_extracti64x2_epi64(lo_2, 1);
-    let final_2 = _mm_extract_epi64(l, 1) as u64;
-    let final_3 = _mm_cvtsi128_si64(l) as u64;
-
-    let l = _mm512_extracti64x2_epi64(hi_2, 0);
-    let final_4 = _mm_extract_epi64(l, 1) as u64;
-    let final_5 = _mm_cvtsi128_si64(l) as u64;
-
-    let l = _mm512_extracti64x2_epi64(lo_2, 0);
-    let final_6 = _mm_extract_epi64(l, 1) as u64;
-    let final_7 = _mm_cvtsi128_si64(l) as u64;
-
-    //row1
-    let l = _mm512_extracti64x2_epi64(hi_2, 3);
-    let final1_0 = _mm_extract_epi64(l, 1) as u64;
-    let final1_1 = _mm_cvtsi128_si64(l) as u64;
-
-    let l = _mm512_extracti64x2_epi64(lo_2, 3);
-    let final1_2 = _mm_extract_epi64(l, 1) as u64;
-    let final1_3 = _mm_cvtsi128_si64(l) as u64;
-
-    let l = _mm512_extracti64x2_epi64(hi_2, 2);
-    let final1_4 = _mm_extract_epi64(l, 1) as u64;
-    let final1_5 = _mm_cvtsi128_si64(l) as u64;
-
-    let l = _mm512_extracti64x2_epi64(lo_2, 2);
-    let final1_6 = _mm_extract_epi64(l, 1) as u64;
-    let final1_7 = _mm_cvtsi128_si64(l) as u64;
-
-    let row0 = [
-        final_0, final_1, final_2, final_3, final_4, final_5, final_6, final_7,
-    ];
-    let row1 = [
-        final1_0, final1_1, final1_2, final1_3, final1_4, final1_5, final1_6, final1_7,
-    ];
-    (row0, row1)
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+/// # Safety
+/// The cpu must support avx2 (see `transpose_64x64_inplace` for a checked version).
+pub unsafe fn transpose_avx2_inplace(matrix: &mut [u64; 64]) {
+    avx2_store(avx2_transpose_registers(matrix), matrix);
 }
 
-#[target_feature(enable = "avx512f")]
-unsafe fn grab_128_lanes_a(a: __m512i, b: __m512i) -> __m512i {
-    let idx = _mm512_set_epi64(
-        13, 12, // B2
-        5, 4, // A2
-        9, 8, // B0
-        1, 0, // A0
-    );
-
-    _mm512_permutex2var_epi64(a, idx, b)
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn avx2_store(v: [__m256i; 16], output: &mut [u64; 64]) {
+    let ptr = output.as_mut_ptr();
+    for (i, row) in v.iter().enumerate() {
+        unsafe { _mm256_storeu_si256(ptr.add(i * 4) as *mut _, *row) };
+    }
 }
 
-#[target_feature(enable = "avx512f")]
-unsafe fn grab_128_lanes_b(a: __m512i, b: __m512i) -> __m512i {
-    let idx = _mm512_set_epi64(
-        15, 14, // B3
-        7, 6, // A3
-        11, 10, // B1
-        3, 2, // A1
-    );
-
-    _mm512_permutex2var_epi64(a, idx, b)
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn avx2_transpose_registers(input: &[u64; 64]) -> [__m256i; 16] {
+    //same butterfly as transpose_scalar, but on 4 rows at once (the whole matrix fits in 16 registers)
+    let mut v = [_mm256_setzero_si256(); 16];
+    let ptr = input.as_ptr();
+    for (i, row) in v.iter_mut().enumerate() {
+        *row = unsafe { _mm256_loadu_si256(ptr.add(i * 4) as *const _) };
+    }
+
+    //rows k and k | j are in different registers
+    unsafe {
+        avx2_stage::<32>(&mut v, 0x00000000FFFFFFFF);
+        avx2_stage::<16>(&mut v, 0x0000FFFF0000FFFF);
+        avx2_stage::<8>(&mut v, 0x00FF00FF00FF00FF);
+        avx2_stage::<4>(&mut v, 0x0F0F0F0F0F0F0F0F);
+    }
+
+    //rows k and k | j are in the same register, regroup pairs of registers so they line up
+    for pair in v.chunks_exact_mut(2) {
+        let x = _mm256_permute2x128_si256(pair[0], pair[1], 0x20);
+        let y = _mm256_permute2x128_si256(pair[0], pair[1], 0x31);
+        let (x, y) = unsafe { avx2_butterfly::<2>(x, y, 0x3333333333333333) };
+        pair[0] = _mm256_permute2x128_si256(x, y, 0x20);
+        pair[1] = _mm256_permute2x128_si256(x, y, 0x31);
+    }
+    for pair in v.chunks_exact_mut(2) {
+        let x = _mm256_unpacklo_epi64(pair[0], pair[1]);
+        let y = _mm256_unpackhi_epi64(pair[0], pair[1]);
+        let (x, y) = unsafe { avx2_butterfly::<1>(x, y, 0x5555555555555555) };
+        pair[0] = _mm256_unpacklo_epi64(x, y);
+        pair[1] = _mm256_unpackhi_epi64(x, y);
+    }
+    v
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn avx2_stage<const J: i32>(v: &mut [__m256i; 16], mask: u64) {
+    let d = J as usize / 4;
+    for k in 0..16 {
+        if (k & d) == 0 {
+            let (x, y) = unsafe { avx2_butterfly::<J>(v[k], v[k | d], mask) };
+            v[k] = x;
+            v[k | d] = y;
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn avx2_butterfly<const J: i32>(x: __m256i, y: __m256i, mask: u64) -> (__m256i, __m256i) {
+    let mask = _mm256_set1_epi64x(mask as i64);
+    let t = _mm256_and_si256(_mm256_xor_si256(x, _mm256_srli_epi64::<J>(y)), mask);
+    (
+        _mm256_xor_si256(x, t),
+        _mm256_xor_si256(y, _mm256_slli_epi64::<J>(t)),
+    )
+}
+
+#[cfg(target_arch = "aarch64")]
+pub fn transpose_neon(input: &[u64; 64]) -> [u64; 64] {
+    let mut output = [0u64; 64];
+    //neon is part of the aarch64 baseline, so unlike the x86 backends this needs no runtime check
+    unsafe { neon_store(neon_transpose_registers(input), &mut output) };
+    output
 }
 
-unsafe fn print_m512i_bits(v: __m512i) {
-    let mut buf = [0u64; 8];
-    _mm512_storeu_si512(buf.as_mut_ptr() as *mut _, v);
+#[cfg(target_arch = "aarch64")]
+pub fn transpose_neon_inplace(matrix: &mut [u64; 64]) {
+    unsafe { neon_store(neon_transpose_registers(matrix), matrix) };
+}
 
-    // Print highest bit first
-    for &x in buf.iter().rev() {
-        print!("{:064b}", x);
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn neon_store(v: [uint64x2_t; 32], output: &mut [u64; 64]) {
+    let ptr = output.as_mut_ptr();
+    for (i, row) in v.iter().enumerate() {
+        unsafe { vst1q_u64(ptr.add(i * 2), *row) };
     }
-    println!();
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn neon_transpose_registers(input: &[u64; 64]) -> [uint64x2_t; 32] {
+    //register i holds rows 2i and 2i + 1 (the whole matrix fits in the 32 neon registers)
+    let mut v = [vdupq_n_u64(0); 32];
+    let ptr = input.as_ptr();
+    for (i, row) in v.iter_mut().enumerate() {
+        *row = unsafe { vld1q_u64(ptr.add(i * 2)) };
+    }
+
+    //first transpose the bytes of every 8x8 byte block with byte shuffles:
+    //the 32, 16 and 8 bit butterfly stages just swap whole u32s, u16s and bytes between rows
+    for k in (0..32).filter(|k| k & 16 == 0) {
+        let (x, y) = (
+            vreinterpretq_u32_u64(v[k]),
+            vreinterpretq_u32_u64(v[k | 16]),
+        );
+        v[k] = vreinterpretq_u64_u32(vtrn2q_u32(y, x));
+        v[k | 16] = vreinterpretq_u64_u32(vtrn1q_u32(y, x));
+    }
+    for k in (0..32).filter(|k| k & 8 == 0) {
+        let (x, y) = (vreinterpretq_u16_u64(v[k]), vreinterpretq_u16_u64(v[k | 8]));
+        v[k] = vreinterpretq_u64_u16(vtrn2q_u16(y, x));
+        v[k | 8] = vreinterpretq_u64_u16(vtrn1q_u16(y, x));
+    }
+    for k in (0..32).filter(|k| k & 4 == 0) {
+        let (x, y) = (vreinterpretq_u8_u64(v[k]), vreinterpretq_u8_u64(v[k | 4]));
+        v[k] = vreinterpretq_u64_u8(vtrn2q_u8(y, x));
+        v[k | 4] = vreinterpretq_u64_u8(vtrn1q_u8(y, x));
+    }
+
+    //then transpose every 8x8 bit block (the remaining 4, 2 and 1 bit stages)
+    for k in (0..32).filter(|k| k & 2 == 0) {
+        (v[k], v[k | 2]) = unsafe { neon_butterfly::<4>(v[k], v[k | 2], 0x0F0F0F0F0F0F0F0F) };
+    }
+    for k in (0..32).filter(|k| k & 1 == 0) {
+        (v[k], v[k | 1]) = unsafe { neon_butterfly::<2>(v[k], v[k | 1], 0x3333333333333333) };
+    }
+    //rows 2i and 2i + 1 are in the same register, regroup pairs of registers so they line up
+    for pair in v.chunks_exact_mut(2) {
+        let x = vtrn1q_u64(pair[0], pair[1]);
+        let y = vtrn2q_u64(pair[0], pair[1]);
+        let (x, y) = unsafe { neon_butterfly::<1>(x, y, 0x5555555555555555) };
+        pair[0] = vtrn1q_u64(x, y);
+        pair[1] = vtrn2q_u64(x, y);
+    }
+    v
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn neon_butterfly<const J: i32>(
+    x: uint64x2_t,
+    y: uint64x2_t,
+    mask: u64,
+) -> (uint64x2_t, uint64x2_t) {
+    let t = vandq_u64(veorq_u64(x, vshrq_n_u64::<J>(y)), vdupq_n_u64(mask));
+    (veorq_u64(x, t), veorq_u64(y, vshlq_n_u64::<J>(t)))
 }
 
 pub fn print_bit_matrix(matrix: &[u64]) {
@@ -226,15 +425,106 @@ pub fn print_bit_matrix(matrix: &[u64]) {
 }
 
 #[cfg(test)]
+#[allow(
+    clippy::needless_range_loop,
+    clippy::bool_assert_comparison,
+    clippy::unnecessary_cast
+)]
 mod tests {
     use super::*;
+    use crate::test_util::random_matrices;
+
+    //portable models of the gfni kernels, so the pipeline can be checked on any machine
+
+    //_mm512_permutexvar_epi8: byte i of the result is byte perm[i] of v
+    fn permute_bytes_model(perm: &[u8; 64], v: &[u64; 8]) -> [u64; 8] {
+        let bytes: Vec<u8> = v.iter().flat_map(|q| q.to_le_bytes()).collect();
+        std::array::from_fn(|q| {
+            u64::from_le_bytes(std::array::from_fn(|b| {
+                bytes[perm[q * 8 + b] as usize & 63]
+            }))
+        })
+    }
+
+    //_mm512_gf2p8affine_epi64_epi8(x, a, 0) on one qword: bit i of byte j is parity(a.byte[7 - i] & x.byte[j])
+    fn gf2p8affine_model(x: u64, a: u64) -> u64 {
+        let x = x.to_le_bytes();
+        let a = a.to_le_bytes();
+        u64::from_le_bytes(std::array::from_fn(|j| {
+            (0..8).fold(0u8, |acc, i| {
+                acc | (((a[7 - i] & x[j]).count_ones() & 1) as u8) << i
+            })
+        }))
+    }
 
+    fn gfni_bit_transpose_8x8_model(v: &[u64; 8]) -> [u64; 8] {
+        v.map(|q| gf2p8affine_model(0x8040201008040201, q))
+    }
+
+    fn transpose_8x8_qwords_model(v: &[[u64; 8]; 8]) -> [[u64; 8]; 8] {
+        std::array::from_fn(|i| std::array::from_fn(|j| v[j][i]))
+    }
+
+    //transpose_gfni step by step
+    fn transpose_gfni_model(input: &[u64; 64]) -> [u64; 64] {
+        let blocks: [[u64; 8]; 8] = std::array::from_fn(|i| {
+            let rows = input[i * 8..i * 8 + 8].try_into().unwrap();
+            gfni_bit_transpose_8x8_model(&permute_bytes_model(&GATHER_PERM, rows))
+        });
+        let blocks = transpose_8x8_qwords_model(&blocks);
+        let mut output = [0u64; 64];
+        for (rows, block) in output.chunks_exact_mut(8).zip(&blocks) {
+            rows.copy_from_slice(&permute_bytes_model(&SCATTER_PERM, block));
+        }
+        output
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn gfni_supported() -> bool {
+        let supported = std::is_x86_feature_detected!("gfni")
+            && std::is_x86_feature_detected!("avx512f")
+            && std::is_x86_feature_detected!("avx512bw")
+            && std::is_x86_feature_detected!("avx512vbmi");
+        if !supported {
+            eprintln!("skipping, the cpu doesn't support avx512 + gfni");
+        }
+        supported
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn to_m512i(v: &[u64; 8]) -> __m512i {
+        unsafe { _mm512_loadu_si512(v.as_ptr() as *const _) }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn from_m512i(v: __m512i) -> [u64; 8] {
+        let mut out = [0u64; 8];
+        unsafe { _mm512_storeu_si512(out.as_mut_ptr() as *mut _, v) };
+        out
+    }
+
+    #[test]
+    fn test_gfni_models_work() {
+        //same expectations as the hardware tests below
+        let mut rows = [0u64; 8];
+        rows[0] = 0xFF00000000000000;
+        let expected = 0b0000000100000001000000010000000100000001000000010000000100000001;
+        assert_eq!(gfni_bit_transpose_8x8_model(&rows)[0], expected);
+
+        for input in random_matrices(100) {
+            assert_eq!(transpose_gfni_model(&input), transpose_scalar(&input));
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
     unsafe fn m512i_eq(a: __m512i, b: __m512i) -> bool {
-        let mut aa = [0u64; 8];
-        let mut bb = [0u64; 8];
-        _mm512_storeu_si512(aa.as_mut_ptr() as *mut _, a);
-        _mm512_storeu_si512(bb.as_mut_ptr() as *mut _, b);
-        aa == bb
+        unsafe {
+            let mut aa = [0u64; 8];
+            let mut bb = [0u64; 8];
+            _mm512_storeu_si512(aa.as_mut_ptr() as *mut _, a);
+            _mm512_storeu_si512(bb.as_mut_ptr() as *mut _, b);
+            aa == bb
+        }
     }
 
     #[test]
@@ -257,8 +547,117 @@ mod tests {
         assert_eq!(transposed[63], 0);
     }
 
+    //bit by bit, row r column c is bit width - 1 - c of row r
+    fn transpose_naive<const N: usize>(input: &[u128; N]) -> [u128; N] {
+        let mut out = [0u128; N];
+        for (r, row) in input.iter().enumerate() {
+            for (c, out_row) in out.iter_mut().enumerate() {
+                *out_row |= (row >> (N - 1 - c) & 1) << (N - 1 - r);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_transpose_square_scalar_works() {
+        for input in random_matrices(20) {
+            let widened = input.map(|row| row as u128);
+            assert_eq!(
+                transpose_scalar(&input).map(|row| row as u128),
+                transpose_naive(&widened)
+            );
+
+            let rows: [u8; 8] = std::array::from_fn(|i| input[i] as u8);
+            let expected = transpose_naive(&rows.map(|row| row as u128));
+            assert_eq!(transpose_8x8_scalar(&rows).map(|row| row as u128), expected);
+
+            let rows: [u32; 32] = std::array::from_fn(|i| input[i] as u32);
+            let expected = transpose_naive(&rows.map(|row| row as u128));
+            assert_eq!(
+                transpose_32x32_scalar(&rows).map(|row| row as u128),
+                expected
+            );
+
+            let rows: [u128; 128] = std::array::from_fn(|i| {
+                (input[i / 2] as u128) << (i % 2 * 64) | input[63 - i / 2] as u128
+            });
+            assert_eq!(transpose_128x128_scalar(&rows), transpose_naive(&rows));
+        }
+    }
+
     #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_transpose_8x8_gfni_works() {
+        if !std::is_x86_feature_detected!("gfni") {
+            return;
+        }
+        for input in random_matrices(1)[0] {
+            let rows = input.to_le_bytes();
+            assert_eq!(
+                unsafe { transpose_8x8_gfni(&rows) },
+                transpose_8x8_scalar(&rows)
+            );
+        }
+    }
+
+    #[test]
+    fn test_transpose_scalar_roundtrip_works() {
+        for input in random_matrices(100) {
+            let transposed = transpose_scalar(&input);
+            let mut matrix = input;
+            transpose_scalar_inplace(&mut matrix);
+            assert_eq!(matrix, transposed);
+            //bit 63 - c of row r ends up in bit 63 - r of row c
+            assert_eq!(transposed[3] >> (63 - 5) & 1, input[5] >> (63 - 3) & 1);
+            assert_eq!(transpose_scalar(&transposed), input);
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_transpose_avx2_works() {
+        if !std::is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let mut input = [0u64; 64];
+        input[0] = 0xFFFFFFFFFFFFFFF0;
+        input[1] = 0xFFFFFFFFFFFFFFF0;
+        assert_eq!(unsafe { transpose_avx2(&input) }, transpose_scalar(&input));
+
+        for input in random_matrices(100) {
+            let transposed = unsafe { transpose_avx2(&input) };
+            assert_eq!(transposed, transpose_scalar(&input));
+            let mut matrix = input;
+            unsafe { transpose_avx2_inplace(&mut matrix) };
+            assert_eq!(matrix, transposed);
+            assert_eq!(unsafe { transpose_avx2(&transposed) }, input);
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn test_transpose_neon_works() {
+        let mut input = [0u64; 64];
+        input[0] = 0xFFFFFFFFFFFFFFF0;
+        input[1] = 0xFFFFFFFFFFFFFFF0;
+        assert_eq!(transpose_neon(&input), transpose_scalar(&input));
+
+        for input in random_matrices(100) {
+            let transposed = transpose_neon(&input);
+            assert_eq!(transposed, transpose_scalar(&input));
+            let mut matrix = input;
+            transpose_neon_inplace(&mut matrix);
+            assert_eq!(matrix, transposed);
+            assert_eq!(transpose_neon(&transposed), input);
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
     fn test_gather_bytes() {
+        if !gfni_supported() {
+            return;
+        }
         let mut input = [0u64; 64];
         input[0] = 0xFFFFFFFFFFFFFFFF;
         input[1] = 0xFF;
@@ -279,15 +678,25 @@ mod tests {
         }
         print_bit_matrix(&output);
 
-        unsafe {
-            let output2 = gather_bytes(&input);
-            print_bit_matrix(&output2);
-            assert_eq!(output, output2);
+        let mut output2 = [0u64; 64];
+        for (j, block) in input.chunks_exact(8).enumerate() {
+            let block: &[u64; 8] = block.try_into().unwrap();
+            let gathered = from_m512i(unsafe { gfni_gather_bytes(to_m512i(block)) });
+            assert_eq!(gathered, permute_bytes_model(&GATHER_PERM, block));
+            for (i, qword) in gathered.iter().enumerate() {
+                output2[i * 8 + j] = *qword;
+            }
         }
+        print_bit_matrix(&output2);
+        assert_eq!(output, output2);
     }
 
     #[test]
+    #[cfg(target_arch = "x86_64")]
     fn test_unpack() {
+        if !gfni_supported() {
+            return;
+        }
         let mut input = [0u64; 64];
         input[0] = 0xFFFFFFFFFFFFFFF0;
         input[1] = 0xFFFFFFFFFFFFFFF0;
@@ -340,19 +749,13 @@ mod tests {
     A00B00...H00 (64bits length for every row)
     A01B01...H01
     ...
-    Interleave bytes
-    A00B00A01B01A02B02A03B03
-    A04B04A05B05A06B06A07B07
-    C00D00C01D01C02D02C03D03
-    C04D04C05D05C06D06C07D07
-
-    A00B00C00D00A01B01C01D01
-    A02B02C02D02A03B03C03D03
-    A04B04C04D04A05B05C05D05
-    A06B06C06D06A07B07C07D07
     */
     #[test]
+    #[cfg(target_arch = "x86_64")]
     fn test_gfni() {
+        if !gfni_supported() {
+            return;
+        }
         let mut gfni_input = [0u64; 8];
         gfni_input[0] = 0xFF00000000000000;
         unsafe {
@@ -379,95 +782,76 @@ mod tests {
             );
             assert_eq!(m512i_eq(output, expected), true);
         }
+        for input in random_matrices(10) {
+            for rows in input.chunks_exact(8) {
+                let rows = rows.try_into().unwrap();
+                let output = unsafe { gfni_bit_transpose_8x8(to_m512i(rows)) };
+                assert_eq!(from_m512i(output), gfni_bit_transpose_8x8_model(rows));
+            }
+        }
     }
 
     #[test]
-    fn test_interleave() {
-        let a: u64 = 0b1100000011000000110000001100000011000000110000001100000011000000;
-        let expected = 0b1100000000000000000000000000000000000000000000000000000000000000;
+    #[cfg(target_arch = "x86_64")]
+    fn test_transpose_gfni_x8_works() {
+        if !gfni_supported() {
+            return;
+        }
+        for matrices in random_matrices(80).chunks_exact(8) {
+            let input: Vec<u64> = matrices.iter().flatten().copied().collect();
+            let rows = unsafe { transpose_gfni_x8(input.as_slice().try_into().unwrap()) };
+            for (j, matrix) in matrices.iter().enumerate() {
+                let expected = transpose_scalar(matrix);
+                for (row, bits) in rows.iter().zip(expected) {
+                    assert_eq!(row.to_array()[j], bits);
+                }
+            }
+        }
+    }
 
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_transpose_8x8_qwords() {
+        if !gfni_supported() {
+            return;
+        }
         unsafe {
-            let va = _mm512_set_epi64(0, 0, 0, 0, 0, 0, 0, 0);
-            let vb = _mm512_set_epi64(0, 0, 0, a as i64, 0, 0, 0, a as i64);
-            let interleaved = interleave_full(va, vb);
-            for i in interleaved.0 {
-                assert_eq!(expected, i);
+            let mut v = [_mm512_setzero_si512(); 8];
+            for (i, register) in v.iter_mut().enumerate() {
+                let i = i as i64 * 8;
+                *register = _mm512_set_epi64(i + 7, i + 6, i + 5, i + 4, i + 3, i + 2, i + 1, i);
             }
-            for i in interleaved.1 {
-                assert_eq!(expected, i);
+            transpose_8x8_qwords(&mut v);
+            for (i, register) in v.iter().enumerate() {
+                let mut qwords = [0u64; 8];
+                _mm512_storeu_si512(qwords.as_mut_ptr() as *mut _, *register);
+                for (j, qword) in qwords.iter().enumerate() {
+                    assert_eq!(*qword, (j * 8 + i) as u64);
+                }
             }
         }
+        let input = random_matrices(1)[0];
+        let blocks: [[u64; 8]; 8] =
+            std::array::from_fn(|i| input[i * 8..i * 8 + 8].try_into().unwrap());
+        let mut v = blocks.map(|block| to_m512i(&block));
+        unsafe { transpose_8x8_qwords(&mut v) };
+        assert_eq!(v.map(from_m512i), transpose_8x8_qwords_model(&blocks));
     }
 
     #[test]
-    fn test_grab_128_lanes() {
-        let ones: u64 = 0xFFFFFFFFFFFFFFFF;
-        let unique: u64 = 0xFF;
-        unsafe {
-            let a = _mm512_set_epi64(
-                0,
-                0,
-                ones as i64,
-                ones as i64,
-                0,
-                0,
-                ones as i64,
-                ones as i64,
-            );
-            let b = _mm512_set_epi64(
-                0,
-                0,
-                unique as i64,
-                unique as i64,
-                0,
-                0,
-                unique as i64,
-                unique as i64,
-            );
-            let output = grab_128_lanes_a(a, b);
-            let expected = _mm512_set_epi64(
-                unique as i64,
-                unique as i64,
-                ones as i64,
-                ones as i64,
-                unique as i64,
-                unique as i64,
-                ones as i64,
-                ones as i64,
-            );
-            assert_eq!(m512i_eq(output, expected), true);
-            let a = _mm512_set_epi64(
-                ones as i64,
-                ones as i64,
-                0,
-                0,
-                ones as i64,
-                ones as i64,
-                0,
-                0,
-            );
-            let b = _mm512_set_epi64(
-                unique as i64,
-                unique as i64,
-                0,
-                0,
-                unique as i64,
-                unique as i64,
-                0,
-                0,
-            );
-            let output = grab_128_lanes_b(a, b);
-            let expected = _mm512_set_epi64(
-                unique as i64,
-                unique as i64,
-                ones as i64,
-                ones as i64,
-                unique as i64,
-                unique as i64,
-                ones as i64,
-                ones as i64,
-            );
-            assert_eq!(m512i_eq(output, expected), true);
+    #[cfg(target_arch = "x86_64")]
+    fn test_transpose_gfni_works() {
+        if !gfni_supported() {
+            return;
+        }
+        for input in random_matrices(100) {
+            let transposed = unsafe { transpose_gfni(&input) };
+            assert_eq!(transposed, transpose_scalar(&input));
+            assert_eq!(transposed, transpose_gfni_model(&input));
+            let mut matrix = input;
+            unsafe { transpose_gfni_inplace(&mut matrix) };
+            assert_eq!(matrix, transposed);
+            assert_eq!(unsafe { transpose_gfni(&transposed) }, input);
         }
     }
 }